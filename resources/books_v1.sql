@@ -0,0 +1,63 @@
+CREATE TABLE publishers (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE
+);
+CREATE TABLE authors (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    forename TEXT NOT NULL,
+    surname TEXT NOT NULL,
+    UNIQUE(forename, surname)
+);
+CREATE TABLE books (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    publisher_id INTEGER,
+    title TEXT NOT NULL,
+    edition TEXT,
+    date_published TEXT NOT NULL,
+    original_date_published TEXT,
+    price DECIMAL,
+    binding TEXT NOT NULL,
+    isbn TEXT NOT NULL,
+    pages INTEGER NOT NULL DEFAULT 0,
+    owned INTEGER NOT NULL DEFAULT 0,
+    UNIQUE(title, edition),
+    FOREIGN KEY (publisher_id) REFERENCES publishers(id)
+);
+CREATE TABLE books_authors (
+    book_id INTEGER,
+    author_id INTEGER,
+    FOREIGN KEY (book_id) REFERENCES books(id),
+    FOREIGN KEY (author_id) REFERENCES authors(id)
+);
+
+INSERT INTO publishers (id, name) VALUES (1, 'Carlton Publishing Group');
+INSERT INTO publishers (id, name) VALUES (2, 'Crown');
+
+INSERT INTO authors (id, forename, surname) VALUES (1, 'Simon', 'Reeve');
+INSERT INTO authors (id, forename, surname) VALUES (2, 'Jim', 'Dwyer');
+INSERT INTO authors (id, forename, surname) VALUES (3, 'Deidre', 'Murphy');
+INSERT INTO authors (id, forename, surname) VALUES (4, 'Peg', 'Tyre');
+INSERT INTO authors (id, forename, surname) VALUES (5, 'David', 'Kocieniewski');
+
+INSERT INTO books (
+    id, publisher_id, title, edition, date_published, original_date_published,
+    price, binding, isbn, pages, owned
+) VALUES (
+    1, 1, 'The New Jackals: Osama Bin Laden and the Future of Terrorism', '2nd', '2001',
+    '1999', 20.0, 'Paperback', '9780233050485', 352, 1
+);
+INSERT INTO books (
+    id, publisher_id, title, edition, date_published, original_date_published,
+    price, binding, isbn, pages, owned
+) VALUES (
+    2, 2, 'Two Seconds Under the World:Terror Comes to America-The Conspiracy Behind the World Trade Center Bombing',
+    '1st', '1997', NULL, NULL, 'Hardcover', '9780517597675', 322, 1
+);
+
+INSERT INTO books_authors (book_id, author_id) VALUES (1, 1);
+INSERT INTO books_authors (book_id, author_id) VALUES (2, 2);
+INSERT INTO books_authors (book_id, author_id) VALUES (2, 3);
+INSERT INTO books_authors (book_id, author_id) VALUES (2, 4);
+INSERT INTO books_authors (book_id, author_id) VALUES (2, 5);
+
+PRAGMA user_version = 1;
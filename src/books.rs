@@ -1,23 +1,40 @@
+use crate::covers::Cover;
 use crate::error::{Error, Result};
+use crate::isbn::Isbn;
 use crate::models::AddBookModel;
 
+use clap::ValueEnum;
+use prettytable::{Cell, Row, Table};
 use std::convert::TryFrom;
 use std::path::PathBuf;
+use std::str::FromStr;
 
-#[derive(Debug)]
+const WRAP_LENGTH: usize = 80;
+const LIST_WRAP_LENGTH: usize = 50;
+
+#[derive(Debug, Default)]
 pub struct Author {
     pub id: u32,
     pub forename: String,
     pub surname: String,
 }
 
-#[derive(Debug)]
+impl std::fmt::Display for Author {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.forename.is_empty() {
+            return write!(f, "{}", self.surname);
+        }
+        write!(f, "{}, {}", self.surname, self.forename)
+    }
+}
+
+#[derive(Debug, Default)]
 pub struct Publisher {
     pub id: u32,
     pub name: String,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Book {
     pub id: u32,
     pub authors: Vec<Author>,
@@ -28,22 +45,423 @@ pub struct Book {
     pub original_date_published: Option<String>,
     pub price: Option<f64>,
     pub binding: String,
-    pub isbn: String,
+    pub isbn: Option<String>,
     pub pages: u32,
     pub owned: bool,
+    /// The reader's rating out of 5
+    pub rating: Option<u8>,
+    /// The reading status, such as read, currently-reading or to-read
+    pub shelf: Option<String>,
+    pub date_read: Option<String>,
+    /// Tags for grouping books, such as the subjects given by a metadata provider
+    pub tags: Vec<String>,
+    /// The title without its subtitle, when the provider gives one
+    pub short_title: Option<String>,
+    pub synopsis: Option<String>,
+    pub language: Option<String>,
+    pub dimensions: Option<String>,
+    pub image_url: Option<String>,
+    /// The manufacturer's suggested retail price
+    pub msrp: Option<f64>,
+    /// The ISBN-10 given by the metadata provider
+    pub isbn10: Option<String>,
+    /// The cover image downloaded from the image URL
+    pub cover: Option<Cover>,
+}
+
+impl Book {
+    pub fn authors_display(&self) -> String {
+        self.authors
+            .iter()
+            .map(|a| a.to_string())
+            .collect::<Vec<String>>()
+            .join("; ")
+    }
+
+    /// The first four-digit number in the publication date, which is taken to be the year.
+    pub fn year_published(&self) -> Option<String> {
+        self.date_published
+            .split(|c: char| !c.is_ascii_digit())
+            .find(|part| part.len() == 4)
+            .map(str::to_string)
+    }
+
+    /// The ISBN hyphenated for display, or as stored if it can't be hyphenated.
+    pub fn isbn_display(&self) -> String {
+        match &self.isbn {
+            Some(isbn) => isbn
+                .parse::<Isbn>()
+                .and_then(|isbn| isbn.hyphenated())
+                .unwrap_or_else(|_| isbn.clone()),
+            None => "N/A".to_string(),
+        }
+    }
+
+    /// The hyphenated ISBN-10, for books that have one. The stored ISBN-10 is used where there is
+    /// one, otherwise it's derived from the ISBN.
+    pub fn isbn10_display(&self) -> Option<String> {
+        let isbn = self.isbn10.as_ref().or(self.isbn.as_ref())?;
+        let isbn = isbn.parse::<Isbn>().ok()?;
+        match isbn.hyphenated_isbn10() {
+            Ok(isbn10) => isbn10,
+            Err(_) => isbn.to_isbn10(),
+        }
+    }
+
+    pub fn print(&self) {
+        let mut table = Table::new();
+        let wrapped_title = textwrap::wrap(&self.title, WRAP_LENGTH).join("\n");
+        table.add_row(Row::new(vec![
+            Cell::new("ID"),
+            Cell::new(&self.id.to_string()),
+        ]));
+        table.add_row(Row::new(vec![
+            Cell::new("Title"),
+            Cell::new(&wrapped_title),
+        ]));
+        if let Some(short_title) = &self.short_title {
+            table.add_row(Row::new(vec![
+                Cell::new("Short Title"),
+                Cell::new(&textwrap::wrap(short_title, WRAP_LENGTH).join("\n")),
+            ]));
+        }
+        table.add_row(Row::new(vec![
+            Cell::new("Author(s)"),
+            Cell::new(&self.authors_display()),
+        ]));
+        table.add_row(Row::new(vec![
+            Cell::new("Publisher"),
+            Cell::new(&self.publisher.name),
+        ]));
+        table.add_row(Row::new(vec![
+            Cell::new("Edition"),
+            Cell::new(&self.edition),
+        ]));
+        table.add_row(Row::new(vec![
+            Cell::new("Date Published"),
+            Cell::new(&self.date_published),
+        ]));
+        table.add_row(Row::new(vec![
+            Cell::new("Original Date Published"),
+            Cell::new(
+                self.original_date_published
+                    .as_ref()
+                    .unwrap_or(&"N/A".to_string()),
+            ),
+        ]));
+        table.add_row(Row::new(vec![
+            Cell::new("Price"),
+            Cell::new(
+                &self
+                    .price
+                    .map_or("N/A".to_string(), |price| format!("{price:.2}")),
+            ),
+        ]));
+        table.add_row(Row::new(vec![
+            Cell::new("MSRP"),
+            Cell::new(
+                &self
+                    .msrp
+                    .map_or("N/A".to_string(), |msrp| format!("{msrp:.2}")),
+            ),
+        ]));
+        table.add_row(Row::new(vec![
+            Cell::new("Binding"),
+            Cell::new(&self.binding),
+        ]));
+        table.add_row(Row::new(vec![
+            Cell::new("Pages"),
+            Cell::new(&self.pages.to_string()),
+        ]));
+        table.add_row(Row::new(vec![
+            Cell::new("Owned"),
+            Cell::new(&self.owned.to_string()),
+        ]));
+        table.add_row(Row::new(vec![
+            Cell::new("Rating"),
+            Cell::new(
+                &self
+                    .rating
+                    .map_or("N/A".to_string(), |rating| format!("{rating}/5")),
+            ),
+        ]));
+        table.add_row(Row::new(vec![
+            Cell::new("Shelf"),
+            Cell::new(self.shelf.as_deref().unwrap_or("N/A")),
+        ]));
+        table.add_row(Row::new(vec![
+            Cell::new("Date Read"),
+            Cell::new(self.date_read.as_deref().unwrap_or("N/A")),
+        ]));
+        let tags = if self.tags.is_empty() {
+            "N/A".to_string()
+        } else {
+            textwrap::wrap(&self.tags.join("; "), WRAP_LENGTH).join("\n")
+        };
+        table.add_row(Row::new(vec![Cell::new("Tags"), Cell::new(&tags)]));
+        table.add_row(Row::new(vec![
+            Cell::new("ISBN"),
+            Cell::new(&self.isbn_display()),
+        ]));
+        if let Some(isbn10) = self.isbn10_display() {
+            table.add_row(Row::new(vec![Cell::new("ISBN-10"), Cell::new(&isbn10)]));
+        }
+        table.add_row(Row::new(vec![
+            Cell::new("Language"),
+            Cell::new(self.language.as_deref().unwrap_or("N/A")),
+        ]));
+        table.add_row(Row::new(vec![
+            Cell::new("Dimensions"),
+            Cell::new(
+                &textwrap::wrap(self.dimensions.as_deref().unwrap_or("N/A"), WRAP_LENGTH)
+                    .join("\n"),
+            ),
+        ]));
+        table.add_row(Row::new(vec![
+            Cell::new("Image URL"),
+            Cell::new(self.image_url.as_deref().unwrap_or("N/A")),
+        ]));
+        table.add_row(Row::new(vec![
+            Cell::new("Cover"),
+            Cell::new(
+                &self
+                    .cover
+                    .as_ref()
+                    .map_or("N/A".to_string(), |cover| cover.path.display().to_string()),
+            ),
+        ]));
+        table.add_row(Row::new(vec![
+            Cell::new("Synopsis"),
+            Cell::new(
+                &textwrap::wrap(self.synopsis.as_deref().unwrap_or("N/A"), WRAP_LENGTH).join("\n"),
+            ),
+        ]));
+        table.printstd();
+    }
+}
+
+/// A tag and the number of books it's applied to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TagCount {
+    pub name: String,
+    pub books: u32,
+}
+
+pub fn print_tags(tags: &[TagCount]) {
+    let mut table = Table::new();
+    table.set_titles(Row::new(vec![Cell::new("Tag"), Cell::new("Books")]));
+    for tag in tags.iter() {
+        table.add_row(Row::new(vec![
+            Cell::new(&tag.name),
+            Cell::new(&tag.books.to_string()),
+        ]));
+    }
+    table.printstd();
+}
+
+/// Tidy a list of tags: surrounding and repeated whitespace is removed, along with empty tags and
+/// any that repeat an earlier one, ignoring case.
+pub fn normalise_tags(tags: Vec<String>) -> Vec<String> {
+    let mut normalised: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.split_whitespace().collect::<Vec<&str>>().join(" ");
+        if !tag.is_empty() && !normalised.iter().any(|t| t.eq_ignore_ascii_case(&tag)) {
+            normalised.push(tag);
+        }
+    }
+    normalised
+}
+
+/// Identifies a book in the database either by its row ID or by its ISBN.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BookKey {
+    Id(u32),
+    Isbn(String),
+}
+
+impl FromStr for BookKey {
+    type Err = Error;
+
+    /// Values that are 10 or 13 characters long, once any hyphens or spaces are removed, are
+    /// treated as ISBNs and normalised to ISBN-13. Anything else must be a numeric ID.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let stripped: String = s
+            .trim()
+            .chars()
+            .filter(|c| *c != '-' && *c != ' ')
+            .collect();
+        if stripped.len() == 10 || stripped.len() == 13 {
+            let isbn: Isbn = stripped.parse()?;
+            return Ok(BookKey::Isbn(isbn.to_isbn13()));
+        }
+        let id = stripped
+            .parse()
+            .map_err(|_| Error::ParseError(format!("{s} is not a valid ID or ISBN")))?;
+        Ok(BookKey::Id(id))
+    }
+}
+
+/// The field used to order the books in a listing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum SortBy {
+    Title,
+    /// The surname of the first author
+    Author,
+    DatePublished,
+    Pages,
+}
+
+/// Criteria for restricting which books appear in a listing.
+///
+/// Fields that are `None` do not restrict the listing. The binding, publisher and tag are matched
+/// without regard to case.
+#[derive(Debug, Default)]
+pub struct BookFilter {
+    pub owned: Option<bool>,
+    pub binding: Option<String>,
+    pub publisher: Option<String>,
+    pub tag: Option<String>,
+}
+
+impl BookFilter {
+    pub fn matches(&self, book: &Book) -> bool {
+        if let Some(owned) = self.owned {
+            if book.owned != owned {
+                return false;
+            }
+        }
+        if let Some(binding) = &self.binding {
+            if !book.binding.eq_ignore_ascii_case(binding) {
+                return false;
+            }
+        }
+        if let Some(publisher) = &self.publisher {
+            if !book.publisher.name.eq_ignore_ascii_case(publisher) {
+                return false;
+            }
+        }
+        if let Some(tag) = &self.tag {
+            if !book.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// A book matched by a full-text search.
+///
+/// The fields contain the indexed text, with the matching terms marked up for display.
+#[derive(Debug)]
+pub struct SearchResult {
+    pub book_id: u32,
+    pub title: String,
+    pub authors: String,
+    pub publisher: String,
+}
+
+const MATCH_START: &str = "\u{1}";
+const MATCH_END: &str = "\u{2}";
+
+impl SearchResult {
+    pub fn print(&self) {
+        println!("[{}] {}", self.book_id, emphasise_matches(&self.title));
+        if !self.authors.is_empty() {
+            println!("    {}", emphasise_matches(&self.authors));
+        }
+        if !self.publisher.is_empty() {
+            println!("    {}", emphasise_matches(&self.publisher));
+        }
+    }
+}
+
+fn emphasise_matches(text: &str) -> String {
+    let mut emphasised = String::new();
+    for (i, part) in text.split(MATCH_START).enumerate() {
+        if i == 0 {
+            emphasised.push_str(part);
+            continue;
+        }
+        let (matched, rest) = part.split_once(MATCH_END).unwrap_or((part, ""));
+        emphasised.push_str(&console::style(matched).bold().yellow().to_string());
+        emphasised.push_str(rest);
+    }
+    emphasised
+}
+
+/// Convert search terms into an FTS5 query.
+///
+/// Each term is quoted, so characters that have a meaning in the query syntax are matched
+/// literally, and treated as a prefix. All the terms must match.
+fn to_fts_query(terms: &[String]) -> String {
+    terms
+        .iter()
+        .flat_map(|term| term.split_whitespace())
+        .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+pub fn print_books(books: &[Book]) {
+    let mut table = Table::new();
+    table.set_titles(Row::new(vec![
+        Cell::new("ID"),
+        Cell::new("Title"),
+        Cell::new("Author(s)"),
+        Cell::new("Publisher"),
+        Cell::new("Date Published"),
+        Cell::new("Binding"),
+        Cell::new("Pages"),
+        Cell::new("Owned"),
+    ]));
+    for book in books.iter() {
+        let wrapped_title = textwrap::wrap(&book.title, LIST_WRAP_LENGTH).join("\n");
+        table.add_row(Row::new(vec![
+            Cell::new(&book.id.to_string()),
+            Cell::new(&wrapped_title),
+            Cell::new(&book.authors_display()),
+            Cell::new(&book.publisher.name),
+            Cell::new(&book.date_published),
+            Cell::new(&book.binding),
+            Cell::new(&book.pages.to_string()),
+            Cell::new(&book.owned.to_string()),
+        ]));
+    }
+    table.printstd();
 }
 
 impl TryFrom<AddBookModel> for Book {
     type Error = Error;
 
     fn try_from(item: AddBookModel) -> Result<Self, Self::Error> {
+        if item.title.trim().is_empty() {
+            return Err(Error::ParseError("The book must have a title".to_string()));
+        }
+        // ISBNs are always stored in their canonical ISBN-13 form.
+        let isbn = item
+            .isbn
+            .map(|isbn| isbn.parse::<Isbn>())
+            .transpose()?
+            .map(|isbn| isbn.to_isbn13());
+        // The ISBN-10 is stored without hyphens. An ISBN-13 is accepted if it has an ISBN-10.
+        let isbn10 =
+            item.isbn10
+                .map(|isbn10| {
+                    isbn10.parse::<Isbn>()?.to_isbn10().ok_or_else(|| {
+                        Error::InvalidIsbn(format!("{isbn10} does not have an ISBN-10"))
+                    })
+                })
+                .transpose()?;
+        // Authors are given as "Surname, Forename". Someone known by a single name is stored with
+        // an empty forename.
         let authors = item
             .authors
             .split(';')
+            .filter(|s| !s.trim().is_empty())
             .map(|s| {
-                let mut split = s.split(',');
-                let surname = split.next().unwrap().trim();
-                let forename = split.next().unwrap().trim();
+                let mut split = s.splitn(2, ',');
+                let surname = split.next().unwrap_or("").trim();
+                let forename = split.next().unwrap_or("").trim();
                 Author {
                     id: 0,
                     forename: forename.to_string(),
@@ -64,9 +482,21 @@ impl TryFrom<AddBookModel> for Book {
             original_date_published: item.original_date_published,
             price: item.price,
             binding: item.binding,
-            isbn: item.isbn,
+            isbn,
             pages: item.pages,
             owned: item.owned,
+            rating: item.rating,
+            shelf: item.shelf,
+            date_read: item.date_read,
+            tags: normalise_tags(item.tags),
+            short_title: item.short_title,
+            synopsis: item.synopsis,
+            language: item.language,
+            dimensions: item.dimensions,
+            image_url: item.image_url,
+            msrp: item.msrp,
+            isbn10,
+            cover: None,
         })
     }
 }
@@ -82,25 +512,119 @@ impl BookRepository {
 
     pub fn add_book(&self, model: AddBookModel) -> Result<Book> {
         let mut book = Book::try_from(model)?;
-        book.publisher.id = crate::db::save_publisher(self.storage_path.clone(), &book.publisher)?;
-        for mut author in book.authors.iter_mut() {
-            author.id = crate::db::save_author(self.storage_path.clone(), author)?;
-        }
-        book.id = crate::db::save_book(self.storage_path.clone(), &book)?;
+        book.id = crate::db::save_book(self.storage_path.clone(), &mut book)?;
+        Ok(book)
+    }
+
+    /// Replace the details of the book with the given ID.
+    ///
+    /// The publisher and authors are resolved again, so correcting a name will link the book to a
+    /// new or different record. Authors and publishers that no longer have any books are deleted.
+    pub fn update_book(&self, id: u32, model: AddBookModel) -> Result<Book> {
+        let mut book = Book::try_from(model)?;
+        book.id = id;
+        crate::db::update_book(self.storage_path.clone(), &mut book)?;
         Ok(book)
     }
 
-    #[allow(dead_code)]
+    /// Delete the book with the given ID.
+    ///
+    /// If `remove_orphans` is set, authors and publishers that no longer have any books are also
+    /// deleted.
+    pub fn remove_book(&self, id: u32, remove_orphans: bool) -> Result<()> {
+        crate::db::delete_book(self.storage_path.clone(), id, remove_orphans)
+    }
+
     pub fn get_by_id(&self, id: u32) -> Result<Book> {
         let book = crate::db::get_book(self.storage_path.clone(), id)?;
         Ok(book)
     }
+
+    pub fn get_by_isbn(&self, isbn: &str) -> Result<Book> {
+        let book = crate::db::get_book_by_isbn(self.storage_path.clone(), isbn)?;
+        Ok(book)
+    }
+
+    /// Find the ID of the book with the given title and edition, if there is one.
+    pub fn find_id_by_title(&self, title: &str, edition: &str) -> Result<Option<u32>> {
+        crate::db::get_book_id_by_title(self.storage_path.clone(), title, edition)
+    }
+
+    pub fn get(&self, key: &BookKey) -> Result<Book> {
+        match key {
+            BookKey::Id(id) => self.get_by_id(*id),
+            BookKey::Isbn(isbn) => self.get_by_isbn(isbn),
+        }
+    }
+
+    /// Record the cover image downloaded for a book.
+    pub fn set_cover(&self, id: u32, cover: &Cover) -> Result<()> {
+        crate::db::save_book_cover(self.storage_path.clone(), id, cover)
+    }
+
+    /// Add tags to a book, returning the book with its updated tags. Tags it already has are
+    /// ignored.
+    pub fn tag_book(&self, key: &BookKey, tags: Vec<String>) -> Result<Book> {
+        let book = self.get(key)?;
+        crate::db::add_book_tags(self.storage_path.clone(), book.id, &normalise_tags(tags))?;
+        self.get_by_id(book.id)
+    }
+
+    /// Remove tags from a book, returning the book with its updated tags. Tags that are no longer
+    /// applied to any book are deleted.
+    pub fn untag_book(&self, key: &BookKey, tags: Vec<String>) -> Result<Book> {
+        let book = self.get(key)?;
+        crate::db::remove_book_tags(self.storage_path.clone(), book.id, &normalise_tags(tags))?;
+        self.get_by_id(book.id)
+    }
+
+    /// Every tag, with the number of books it's applied to, in alphabetical order.
+    pub fn tags(&self) -> Result<Vec<TagCount>> {
+        crate::db::get_tag_counts(self.storage_path.clone())
+    }
+
+    pub fn search(&self, terms: &[String]) -> Result<Vec<SearchResult>> {
+        let query = to_fts_query(terms);
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+        crate::db::search_books(self.storage_path.clone(), &query, MATCH_START, MATCH_END)
+    }
+
+    pub fn list(&self, sort_by: SortBy, filter: &BookFilter) -> Result<Vec<Book>> {
+        let mut books: Vec<Book> = crate::db::get_books(self.storage_path.clone())?
+            .into_iter()
+            .filter(|book| filter.matches(book))
+            .collect();
+        match sort_by {
+            SortBy::Title => books.sort_by(|a, b| a.title.cmp(&b.title)),
+            SortBy::Author => books.sort_by(|a, b| {
+                let a_surname = a.authors.first().map(|a| a.surname.as_str());
+                let b_surname = b.authors.first().map(|b| b.surname.as_str());
+                a_surname
+                    .cmp(&b_surname)
+                    .then_with(|| a.title.cmp(&b.title))
+            }),
+            SortBy::DatePublished => books.sort_by(|a, b| {
+                a.date_published
+                    .cmp(&b.date_published)
+                    .then_with(|| a.title.cmp(&b.title))
+            }),
+            SortBy::Pages => {
+                books.sort_by(|a, b| a.pages.cmp(&b.pages).then_with(|| a.title.cmp(&b.title)))
+            }
+        }
+        Ok(books)
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use super::{Book, BookRepository};
+    use super::{
+        to_fts_query, Book, BookFilter, BookKey, BookRepository, SortBy, MATCH_END, MATCH_START,
+    };
     use crate::db::init_db;
+    use crate::error::Error;
     use crate::models::AddBookModel;
     use assert_fs::prelude::*;
     use color_eyre::Result;
@@ -116,9 +640,10 @@ mod test {
             original_date_published: Some("1999".to_string()),
             price: Some(20.0),
             binding: "Paperback".to_string(),
-            isbn: "9780233050485".to_string(),
+            isbn: Some("9780233050485".to_string()),
             pages: 352,
             owned: true,
+            ..AddBookModel::blank(None)
         };
 
         let book = Book::try_from(model)?;
@@ -144,7 +669,7 @@ mod test {
         assert_eq!(book.original_date_published, Some("1999".to_string()));
         assert_eq!(book.price, Some(20.0));
         assert_eq!(book.binding, "Paperback");
-        assert_eq!(book.isbn, "9780233050485");
+        assert_eq!(book.isbn, Some("9780233050485".to_string()));
         assert_eq!(book.pages, 352);
         assert!(book.owned);
         Ok(())
@@ -161,9 +686,10 @@ mod test {
             original_date_published: None,
             price: Some(20.0),
             binding: "Hardcover".to_string(),
-            isbn: "9780517597675".to_string(),
+            isbn: Some("9780517597675".to_string()),
             pages: 322,
             owned: true,
+            ..AddBookModel::blank(None)
         };
 
         let book = Book::try_from(model)?;
@@ -192,7 +718,7 @@ mod test {
         assert_eq!(book.original_date_published, None);
         assert_eq!(book.price, Some(20.0));
         assert_eq!(book.binding, "Hardcover");
-        assert_eq!(book.isbn, "9780517597675");
+        assert_eq!(book.isbn, Some("9780517597675".to_string()));
         assert_eq!(book.pages, 322);
         assert!(book.owned);
         Ok(())
@@ -213,9 +739,17 @@ mod test {
             original_date_published: Some("1999".to_string()),
             price: Some(20.0),
             binding: "Paperback".to_string(),
-            isbn: "9780233050485".to_string(),
+            isbn: Some("9780233050485".to_string()),
             pages: 352,
             owned: true,
+            short_title: Some("The New Jackals".to_string()),
+            synopsis: Some("A study of the new terrorism.".to_string()),
+            language: Some("en".to_string()),
+            dimensions: Some("Height: 7.7 Inches, Width: 0.8 Inches".to_string()),
+            image_url: Some("https://images.isbndb.com/covers/04/85/9780233050485.jpg".to_string()),
+            msrp: Some(17.75),
+            isbn10: Some("0233050485".to_string()),
+            ..AddBookModel::blank(None)
         };
         let repository = BookRepository::new(books_db_file.to_path_buf());
 
@@ -240,9 +774,26 @@ mod test {
         assert_eq!(book.original_date_published, Some("1999".to_string()));
         assert_eq!(book.price, Some(20.0));
         assert_eq!(book.binding, "Paperback");
-        assert_eq!(book.isbn, "9780233050485");
+        assert_eq!(book.isbn, Some("9780233050485".to_string()));
         assert_eq!(book.pages, 352);
         assert!(book.owned);
+        assert_eq!(book.short_title, Some("The New Jackals".to_string()));
+        assert_eq!(
+            book.synopsis,
+            Some("A study of the new terrorism.".to_string())
+        );
+        assert_eq!(book.language, Some("en".to_string()));
+        assert_eq!(
+            book.dimensions,
+            Some("Height: 7.7 Inches, Width: 0.8 Inches".to_string())
+        );
+        assert_eq!(
+            book.image_url,
+            Some("https://images.isbndb.com/covers/04/85/9780233050485.jpg".to_string())
+        );
+        assert_eq!(book.msrp, Some(17.75));
+        assert_eq!(book.isbn10, Some("0233050485".to_string()));
+        assert_eq!(book.isbn10_display(), Some("0-233-05048-5".to_string()));
 
         Ok(())
     }
@@ -262,9 +813,10 @@ mod test {
             original_date_published: None,
             price: Some(20.0),
             binding: "Hardcover".to_string(),
-            isbn: "9780517597675".to_string(),
+            isbn: Some("9780517597675".to_string()),
             pages: 322,
             owned: true,
+            ..AddBookModel::blank(None)
         };
         let repository = BookRepository::new(books_db_file.to_path_buf());
 
@@ -295,10 +847,592 @@ mod test {
         assert_eq!(book.original_date_published, None);
         assert_eq!(book.price, Some(20.0));
         assert_eq!(book.binding, "Hardcover");
-        assert_eq!(book.isbn, "9780517597675");
+        assert_eq!(book.isbn, Some("9780517597675".to_string()));
         assert_eq!(book.pages, 322);
         assert!(book.owned);
 
         Ok(())
     }
+
+    #[test]
+    fn list_should_return_books_sorted_by_author_surname() -> Result<()> {
+        let storage_dir = assert_fs::TempDir::new().unwrap();
+        let books_db_file = storage_dir.child("books.db");
+        init_db(books_db_file.to_path_buf())?;
+
+        let repository = BookRepository::new(books_db_file.to_path_buf());
+        repository.add_book(AddBookModel {
+            authors: "Reeve, Simon".to_string(),
+            publisher: "Carlton Publishing Group".to_string(),
+            title: "The New Jackals: Osama Bin Laden and the Future of Terrorism".to_string(),
+            edition: "2nd".to_string(),
+            date_published: "2001".to_string(),
+            original_date_published: Some("1999".to_string()),
+            price: Some(20.0),
+            binding: "Paperback".to_string(),
+            isbn: Some("9780233050485".to_string()),
+            pages: 352,
+            owned: true,
+            ..AddBookModel::blank(None)
+        })?;
+        repository.add_book(AddBookModel {
+            authors: "Dwyer, Jim; Murphy, Deidre; Tyre, Peg; Kocieniewski, David".to_string(),
+            publisher: "Crown".to_string(),
+            title: "Two Seconds Under the World:Terror Comes to America-The Conspiracy Behind the World Trade Center Bombing".to_string(),
+            edition: "1st".to_string(),
+            date_published: "1997".to_string(),
+            original_date_published: None,
+            price: Some(20.0),
+            binding: "Hardcover".to_string(),
+            isbn: Some("9780517597675".to_string()),
+            pages: 322,
+            owned: false,
+            ..AddBookModel::blank(None)
+        })?;
+
+        let books = repository.list(SortBy::Author, &BookFilter::default())?;
+        assert_eq!(books.len(), 2);
+        assert_eq!(books[0].authors[0].surname, "Dwyer");
+        assert_eq!(books[0].authors.len(), 4);
+        assert_eq!(books[0].publisher.name, "Crown");
+        assert_eq!(books[1].authors[0].surname, "Reeve");
+        assert_eq!(books[1].publisher.name, "Carlton Publishing Group");
+
+        let books = repository.list(SortBy::Pages, &BookFilter::default())?;
+        assert_eq!(books[0].pages, 322);
+        assert_eq!(books[1].pages, 352);
+
+        Ok(())
+    }
+
+    #[test]
+    fn list_should_return_books_matching_the_filter() -> Result<()> {
+        let storage_dir = assert_fs::TempDir::new().unwrap();
+        let books_db_file = storage_dir.child("books.db");
+        init_db(books_db_file.to_path_buf())?;
+
+        let repository = BookRepository::new(books_db_file.to_path_buf());
+        repository.add_book(AddBookModel {
+            authors: "Reeve, Simon".to_string(),
+            publisher: "Carlton Publishing Group".to_string(),
+            title: "The New Jackals: Osama Bin Laden and the Future of Terrorism".to_string(),
+            edition: "2nd".to_string(),
+            date_published: "2001".to_string(),
+            original_date_published: Some("1999".to_string()),
+            price: Some(20.0),
+            binding: "Paperback".to_string(),
+            isbn: Some("9780233050485".to_string()),
+            pages: 352,
+            owned: true,
+            ..AddBookModel::blank(None)
+        })?;
+        repository.add_book(AddBookModel {
+            authors: "Dwyer, Jim; Murphy, Deidre; Tyre, Peg; Kocieniewski, David".to_string(),
+            publisher: "Crown".to_string(),
+            title: "Two Seconds Under the World:Terror Comes to America-The Conspiracy Behind the World Trade Center Bombing".to_string(),
+            edition: "1st".to_string(),
+            date_published: "1997".to_string(),
+            original_date_published: None,
+            price: Some(20.0),
+            binding: "Hardcover".to_string(),
+            isbn: Some("9780517597675".to_string()),
+            pages: 322,
+            owned: false,
+            ..AddBookModel::blank(None)
+        })?;
+
+        let filter = BookFilter {
+            binding: Some("paperback".to_string()),
+            ..Default::default()
+        };
+        let books = repository.list(SortBy::Title, &filter)?;
+        assert_eq!(books.len(), 1);
+        assert_eq!(books[0].isbn, Some("9780233050485".to_string()));
+
+        let filter = BookFilter {
+            owned: Some(false),
+            ..Default::default()
+        };
+        let books = repository.list(SortBy::Title, &filter)?;
+        assert_eq!(books.len(), 1);
+        assert_eq!(books[0].isbn, Some("9780517597675".to_string()));
+
+        let filter = BookFilter {
+            publisher: Some("Crown".to_string()),
+            owned: Some(true),
+            ..Default::default()
+        };
+        let books = repository.list(SortBy::Title, &filter)?;
+        assert!(books.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn book_key_should_parse_isbns_and_ids() {
+        assert_eq!(
+            "9780233050485".parse::<BookKey>().unwrap(),
+            BookKey::Isbn("9780233050485".to_string())
+        );
+        assert_eq!(
+            "978-0-233-05048-5".parse::<BookKey>().unwrap(),
+            BookKey::Isbn("9780233050485".to_string())
+        );
+        assert_eq!(
+            "0233050485".parse::<BookKey>().unwrap(),
+            BookKey::Isbn("9780233050485".to_string())
+        );
+        assert!(matches!(
+            "023305048X".parse::<BookKey>(),
+            Err(Error::InvalidIsbn(_))
+        ));
+        assert_eq!("42".parse::<BookKey>().unwrap(), BookKey::Id(42));
+        assert!("forty-two".parse::<BookKey>().is_err());
+    }
+
+    #[test]
+    fn get_should_find_a_book_by_id_or_isbn() -> Result<()> {
+        let storage_dir = assert_fs::TempDir::new().unwrap();
+        let books_db_file = storage_dir.child("books.db");
+        init_db(books_db_file.to_path_buf())?;
+
+        let repository = BookRepository::new(books_db_file.to_path_buf());
+        let book = repository.add_book(AddBookModel {
+            authors: "Reeve, Simon".to_string(),
+            publisher: "Carlton Publishing Group".to_string(),
+            title: "The New Jackals: Osama Bin Laden and the Future of Terrorism".to_string(),
+            edition: "2nd".to_string(),
+            date_published: "2001".to_string(),
+            original_date_published: Some("1999".to_string()),
+            price: Some(20.0),
+            binding: "Paperback".to_string(),
+            isbn: Some("9780233050485".to_string()),
+            pages: 352,
+            owned: true,
+            ..AddBookModel::blank(None)
+        })?;
+
+        let by_id = repository.get(&BookKey::Id(book.id))?;
+        let by_isbn = repository.get(&BookKey::Isbn("9780233050485".to_string()))?;
+        assert_eq!(by_id.id, book.id);
+        assert_eq!(by_isbn.id, book.id);
+        assert_eq!(by_isbn.authors[0].surname, "Reeve");
+
+        let result = repository.get(&BookKey::Isbn("9780517597675".to_string()));
+        assert!(matches!(result, Err(Error::BookNotFound(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn to_fts_query_should_quote_each_term_as_a_prefix() {
+        let terms = vec!["new jack".to_string(), "o\"neil".to_string()];
+        assert_eq!(to_fts_query(&terms), "\"new\"* \"jack\"* \"o\"\"neil\"*");
+    }
+
+    #[test]
+    fn search_should_find_books_by_title_author_or_publisher() -> Result<()> {
+        let storage_dir = assert_fs::TempDir::new().unwrap();
+        let books_db_file = storage_dir.child("books.db");
+        init_db(books_db_file.to_path_buf())?;
+
+        let repository = BookRepository::new(books_db_file.to_path_buf());
+        let jackals = repository.add_book(AddBookModel {
+            authors: "Reeve, Simon".to_string(),
+            publisher: "Carlton Publishing Group".to_string(),
+            title: "The New Jackals: Osama Bin Laden and the Future of Terrorism".to_string(),
+            edition: "2nd".to_string(),
+            date_published: "2001".to_string(),
+            original_date_published: Some("1999".to_string()),
+            price: Some(20.0),
+            binding: "Paperback".to_string(),
+            isbn: Some("9780233050485".to_string()),
+            pages: 352,
+            owned: true,
+            ..AddBookModel::blank(None)
+        })?;
+        let two_seconds = repository.add_book(AddBookModel {
+            authors: "Dwyer, Jim; Murphy, Deidre; Tyre, Peg; Kocieniewski, David".to_string(),
+            publisher: "Crown".to_string(),
+            title: "Two Seconds Under the World:Terror Comes to America-The Conspiracy Behind the World Trade Center Bombing".to_string(),
+            edition: "1st".to_string(),
+            date_published: "1997".to_string(),
+            original_date_published: None,
+            price: Some(20.0),
+            binding: "Hardcover".to_string(),
+            isbn: Some("9780517597675".to_string()),
+            pages: 322,
+            owned: false,
+            ..AddBookModel::blank(None)
+        })?;
+
+        let results = repository.search(&["deidre".to_string()])?;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].book_id, two_seconds.id);
+        assert_eq!(
+            results[0].authors,
+            format!(
+                "Jim Dwyer; {MATCH_START}Deidre{MATCH_END} Murphy; Peg Tyre; David Kocieniewski"
+            )
+        );
+
+        let results = repository.search(&["carlton".to_string()])?;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].book_id, jackals.id);
+
+        // Both titles contain a word starting with "terror".
+        let results = repository.search(&["terror".to_string()])?;
+        assert_eq!(results.len(), 2);
+
+        let results = repository.search(&["terror simon".to_string()])?;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].book_id, jackals.id);
+        Ok(())
+    }
+
+    #[test]
+    fn update_book_should_save_changes_and_remove_orphaned_authors_and_publishers() -> Result<()> {
+        let storage_dir = assert_fs::TempDir::new().unwrap();
+        let books_db_file = storage_dir.child("books.db");
+        init_db(books_db_file.to_path_buf())?;
+
+        let repository = BookRepository::new(books_db_file.to_path_buf());
+        let book = repository.add_book(AddBookModel {
+            authors: "Reeve, Simon".to_string(),
+            publisher: "Carlton".to_string(),
+            title: "The New Jackals".to_string(),
+            edition: "2nd".to_string(),
+            date_published: "2001".to_string(),
+            original_date_published: None,
+            price: None,
+            binding: "Paperback".to_string(),
+            isbn: Some("9780233050485".to_string()),
+            pages: 352,
+            owned: true,
+            ..AddBookModel::blank(None)
+        })?;
+
+        let mut model = AddBookModel::from(repository.get_by_id(book.id)?);
+        assert_eq!(model.authors, "Reeve, Simon");
+        model.authors = "Reeve, Simon; Dwyer, Jim".to_string();
+        model.publisher = "Carlton Publishing Group".to_string();
+        model.title = "The New Jackals: Osama Bin Laden and the Future of Terrorism".to_string();
+        model.original_date_published = Some("1999".to_string());
+        model.price = Some(20.0);
+        repository.update_book(book.id, model)?;
+
+        let updated = repository.get_by_id(book.id)?;
+        assert_eq!(
+            updated.title,
+            "The New Jackals: Osama Bin Laden and the Future of Terrorism"
+        );
+        assert_eq!(updated.publisher.name, "Carlton Publishing Group");
+        assert_eq!(updated.authors.len(), 2);
+        assert_eq!(updated.authors[0].id, book.authors[0].id);
+        assert_eq!(updated.authors[1].surname, "Dwyer");
+        assert_eq!(updated.original_date_published, Some("1999".to_string()));
+        assert_eq!(updated.price, Some(20.0));
+
+        let mut model = AddBookModel::from(updated);
+        model.authors = "Dwyer, Jim".to_string();
+        repository.update_book(book.id, model)?;
+
+        let conn = rusqlite::Connection::open(books_db_file.path())?;
+        let author_count: u32 =
+            conn.query_row("SELECT COUNT(*) FROM authors", [], |row| row.get(0))?;
+        let publisher_count: u32 =
+            conn.query_row("SELECT COUNT(*) FROM publishers", [], |row| row.get(0))?;
+        assert_eq!(author_count, 1);
+        assert_eq!(publisher_count, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn update_book_should_return_an_error_for_an_unknown_book() -> Result<()> {
+        let storage_dir = assert_fs::TempDir::new().unwrap();
+        let books_db_file = storage_dir.child("books.db");
+        init_db(books_db_file.to_path_buf())?;
+
+        let repository = BookRepository::new(books_db_file.to_path_buf());
+        let result = repository.update_book(
+            7,
+            AddBookModel {
+                authors: "Reeve, Simon".to_string(),
+                publisher: "Carlton".to_string(),
+                title: "The New Jackals".to_string(),
+                edition: "2nd".to_string(),
+                date_published: "2001".to_string(),
+                original_date_published: None,
+                price: None,
+                binding: "Paperback".to_string(),
+                isbn: Some("9780233050485".to_string()),
+                pages: 352,
+                owned: true,
+                ..AddBookModel::blank(None)
+            },
+        );
+        assert!(matches!(result, Err(Error::BookNotFound(_))));
+
+        // The new author and publisher aren't left behind.
+        let conn = rusqlite::Connection::open(books_db_file.path())?;
+        let author_count: u32 =
+            conn.query_row("SELECT COUNT(*) FROM authors", [], |row| row.get(0))?;
+        let publisher_count: u32 =
+            conn.query_row("SELECT COUNT(*) FROM publishers", [], |row| row.get(0))?;
+        assert_eq!(author_count, 0);
+        assert_eq!(publisher_count, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn add_book_should_save_nothing_when_the_book_cannot_be_saved() -> Result<()> {
+        let storage_dir = assert_fs::TempDir::new().unwrap();
+        let books_db_file = storage_dir.child("books.db");
+        init_db(books_db_file.to_path_buf())?;
+
+        let repository = BookRepository::new(books_db_file.to_path_buf());
+        let model = |authors: &str, publisher: &str, isbn: &str| AddBookModel {
+            authors: authors.to_string(),
+            publisher: publisher.to_string(),
+            title: "The Odyssey".to_string(),
+            date_published: "1996".to_string(),
+            binding: "Paperback".to_string(),
+            isbn: Some(isbn.to_string()),
+            ..AddBookModel::blank(None)
+        };
+        repository.add_book(model("Homer", "Penguin Classics", "9780140268867"))?;
+        // Another edition with the same title and no edition clashes with the first.
+        let result = repository.add_book(model("Wilson, Emily", "Norton", "9780393089059"));
+        assert!(matches!(result, Err(Error::DatabaseError(_))));
+
+        // The new author and publisher aren't left behind.
+        let conn = rusqlite::Connection::open(books_db_file.path())?;
+        let author_count: u32 =
+            conn.query_row("SELECT COUNT(*) FROM authors", [], |row| row.get(0))?;
+        let publisher_count: u32 =
+            conn.query_row("SELECT COUNT(*) FROM publishers", [], |row| row.get(0))?;
+        assert_eq!(author_count, 1);
+        assert_eq!(publisher_count, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn remove_book_should_delete_the_book_and_optionally_its_orphans() -> Result<()> {
+        let storage_dir = assert_fs::TempDir::new().unwrap();
+        let books_db_file = storage_dir.child("books.db");
+        init_db(books_db_file.to_path_buf())?;
+
+        let repository = BookRepository::new(books_db_file.to_path_buf());
+        let jackals = repository.add_book(AddBookModel {
+            authors: "Reeve, Simon".to_string(),
+            publisher: "Carlton Publishing Group".to_string(),
+            title: "The New Jackals: Osama Bin Laden and the Future of Terrorism".to_string(),
+            edition: "2nd".to_string(),
+            date_published: "2001".to_string(),
+            original_date_published: Some("1999".to_string()),
+            price: Some(20.0),
+            binding: "Paperback".to_string(),
+            isbn: Some("9780233050485".to_string()),
+            pages: 352,
+            owned: true,
+            ..AddBookModel::blank(None)
+        })?;
+        let two_seconds = repository.add_book(AddBookModel {
+            authors: "Dwyer, Jim; Murphy, Deidre; Tyre, Peg; Kocieniewski, David".to_string(),
+            publisher: "Crown".to_string(),
+            title: "Two Seconds Under the World:Terror Comes to America-The Conspiracy Behind the World Trade Center Bombing".to_string(),
+            edition: "1st".to_string(),
+            date_published: "1997".to_string(),
+            original_date_published: None,
+            price: Some(20.0),
+            binding: "Hardcover".to_string(),
+            isbn: Some("9780517597675".to_string()),
+            pages: 322,
+            owned: false,
+            ..AddBookModel::blank(None)
+        })?;
+
+        let conn = rusqlite::Connection::open(books_db_file.path())?;
+        let count =
+            |sql: &str| -> rusqlite::Result<u32> { conn.query_row(sql, [], |row| row.get(0)) };
+
+        repository.remove_book(jackals.id, false)?;
+        assert!(matches!(
+            repository.get_by_id(jackals.id),
+            Err(Error::BookNotFound(_))
+        ));
+        assert_eq!(count("SELECT COUNT(*) FROM books_authors")?, 4);
+        assert_eq!(count("SELECT COUNT(*) FROM authors")?, 5);
+        assert_eq!(count("SELECT COUNT(*) FROM publishers")?, 2);
+
+        repository.remove_book(two_seconds.id, true)?;
+        assert_eq!(count("SELECT COUNT(*) FROM books")?, 0);
+        assert_eq!(count("SELECT COUNT(*) FROM books_authors")?, 0);
+        assert_eq!(count("SELECT COUNT(*) FROM authors")?, 0);
+        assert_eq!(count("SELECT COUNT(*) FROM publishers")?, 0);
+
+        assert!(matches!(
+            repository.remove_book(two_seconds.id, true),
+            Err(Error::BookNotFound(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn try_from_should_convert_add_book_model_without_an_isbn_or_forename() -> Result<()> {
+        let model = AddBookModel {
+            authors: "Homer".to_string(),
+            publisher: "Penguin Classics".to_string(),
+            title: "The Odyssey".to_string(),
+            edition: "".to_string(),
+            date_published: "1946".to_string(),
+            original_date_published: None,
+            price: None,
+            binding: "Paperback".to_string(),
+            isbn: None,
+            pages: 365,
+            owned: true,
+            ..AddBookModel::blank(None)
+        };
+
+        let book = Book::try_from(model)?;
+        assert_eq!(book.authors.len(), 1);
+        assert_eq!(book.authors[0].surname, "Homer");
+        assert_eq!(book.authors[0].forename, "");
+        assert_eq!(book.authors_display(), "Homer");
+        assert_eq!(book.isbn, None);
+        Ok(())
+    }
+
+    #[test]
+    fn try_from_should_reject_add_book_model_without_a_title() {
+        let mut model = AddBookModel::blank(None);
+        model.authors = "Reeve, Simon".to_string();
+        assert!(matches!(Book::try_from(model), Err(Error::ParseError(_))));
+    }
+
+    #[test]
+    fn try_from_should_store_the_isbn_as_isbn13() -> Result<()> {
+        let mut model = AddBookModel::blank(Some("0-233-05048-5".to_string()));
+        model.title = "The New Jackals".to_string();
+        let book = Book::try_from(model)?;
+        assert_eq!(book.isbn, Some("9780233050485".to_string()));
+        assert_eq!(book.isbn_display(), "978-0-233-05048-5");
+        assert_eq!(book.isbn10_display(), Some("0-233-05048-5".to_string()));
+
+        let mut model = AddBookModel::blank(Some("0233050486".to_string()));
+        model.title = "The New Jackals".to_string();
+        assert!(matches!(Book::try_from(model), Err(Error::InvalidIsbn(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn try_from_should_store_a_valid_isbn10_without_hyphens() -> Result<()> {
+        let model = |isbn10: &str| AddBookModel {
+            title: "The New Jackals".to_string(),
+            isbn10: Some(isbn10.to_string()),
+            ..AddBookModel::blank(Some("9780233050485".to_string()))
+        };
+        let book = Book::try_from(model("0-233-05048-5"))?;
+        assert_eq!(book.isbn10, Some("0233050485".to_string()));
+        assert_eq!(book.isbn10_display(), Some("0-233-05048-5".to_string()));
+
+        for isbn10 in ["0233050486", "023305048", "9791034748525"] {
+            assert!(
+                matches!(Book::try_from(model(isbn10)), Err(Error::InvalidIsbn(_))),
+                "{isbn10}"
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn add_book_should_save_book_without_an_isbn() -> Result<()> {
+        let storage_dir = assert_fs::TempDir::new().unwrap();
+        let books_db_file = storage_dir.child("books.db");
+        init_db(books_db_file.to_path_buf())?;
+
+        let mut model = AddBookModel::blank(None);
+        model.authors = "Homer".to_string();
+        model.publisher = "Penguin Classics".to_string();
+        model.title = "The Odyssey".to_string();
+        model.date_published = "1946".to_string();
+        model.binding = "Paperback".to_string();
+        let repository = BookRepository::new(books_db_file.to_path_buf());
+
+        let book = repository.add_book(model)?;
+
+        let book = repository.get_by_id(book.id)?;
+        assert_eq!(book.title, "The Odyssey");
+        assert_eq!(book.isbn, None);
+        assert_eq!(book.authors[0].surname, "Homer");
+        assert!(book.owned);
+        Ok(())
+    }
+
+    #[test]
+    fn tags_should_be_saved_with_the_book_and_changed_by_tagging() -> Result<()> {
+        let storage_dir = assert_fs::TempDir::new().unwrap();
+        let books_db_file = storage_dir.child("books.db");
+        init_db(books_db_file.to_path_buf())?;
+
+        let mut model = AddBookModel::blank(Some("9780233050485".to_string()));
+        model.authors = "Reeve, Simon".to_string();
+        model.publisher = "Carlton Publishing Group".to_string();
+        model.title = "The New Jackals".to_string();
+        model.tags = vec![
+            "Terrorism".to_string(),
+            " History  of terrorism ".to_string(),
+            "terrorism".to_string(),
+        ];
+        let repository = BookRepository::new(books_db_file.to_path_buf());
+        let jackals = repository.add_book(model)?;
+        let mut model = AddBookModel::blank(None);
+        model.authors = "Homer".to_string();
+        model.publisher = "Penguin Classics".to_string();
+        model.title = "The Odyssey".to_string();
+        let odyssey = repository.add_book(model)?;
+
+        let book = repository.get_by_id(jackals.id)?;
+        assert_eq!(book.tags, vec!["Terrorism", "History of terrorism"]);
+
+        let book = repository.tag_book(&BookKey::Id(odyssey.id), vec!["Classics".to_string()])?;
+        assert_eq!(book.tags, vec!["Classics"]);
+        let book = repository.tag_book(
+            &BookKey::Id(odyssey.id),
+            vec!["HISTORY OF TERRORISM".to_string(), "Classics".to_string()],
+        )?;
+        // An existing tag keeps the name it was first given.
+        assert_eq!(book.tags, vec!["Classics", "History of terrorism"]);
+
+        let counts = repository.tags()?;
+        let counts: Vec<(&str, u32)> = counts.iter().map(|t| (t.name.as_str(), t.books)).collect();
+        assert_eq!(
+            counts,
+            vec![
+                ("Classics", 1),
+                ("History of terrorism", 2),
+                ("Terrorism", 1)
+            ]
+        );
+
+        let filter = BookFilter {
+            tag: Some("history of terrorism".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(repository.list(SortBy::Title, &filter)?.len(), 2);
+
+        let book = repository.untag_book(
+            &BookKey::Isbn("9780233050485".to_string()),
+            vec!["terrorism".to_string()],
+        )?;
+        assert_eq!(book.tags, vec!["History of terrorism"]);
+        // Tags that are no longer applied to any book are removed.
+        let names: Vec<String> = repository.tags()?.into_iter().map(|t| t.name).collect();
+        assert_eq!(names, vec!["Classics", "History of terrorism"]);
+
+        repository.remove_book(odyssey.id, false)?;
+        let names: Vec<String> = repository.tags()?.into_iter().map(|t| t.name).collect();
+        assert_eq!(names, vec!["History of terrorism"]);
+        Ok(())
+    }
 }
@@ -0,0 +1,124 @@
+use crate::error::Result;
+use prettytable::{Cell, Row, Table};
+use std::path::PathBuf;
+
+/// A summary of the responses cached for a provider.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CacheStats {
+    pub provider: String,
+    pub entries: u32,
+    /// The total size of the cached responses, in bytes
+    pub size: u64,
+    pub oldest: String,
+    pub newest: String,
+}
+
+pub fn print_cache_stats(stats: &[CacheStats]) {
+    let mut table = Table::new();
+    table.set_titles(Row::new(vec![
+        Cell::new("Provider"),
+        Cell::new("Entries"),
+        Cell::new("Size (KB)"),
+        Cell::new("Oldest"),
+        Cell::new("Newest"),
+    ]));
+    for provider in stats.iter() {
+        table.add_row(Row::new(vec![
+            Cell::new(&provider.provider),
+            Cell::new(&provider.entries.to_string()),
+            Cell::new(&format!("{:.1}", provider.size as f64 / 1024.0)),
+            Cell::new(&provider.oldest),
+            Cell::new(&provider.newest),
+        ]));
+    }
+    table.printstd();
+}
+
+/// Stores the raw responses from metadata providers in the database, keyed by provider and ISBN.
+///
+/// Caching the raw response rather than the parsed record means improvements to the parsing
+/// apply to books that have already been looked up.
+pub struct ResponseCache {
+    pub database_path: PathBuf,
+    /// Ignore any cached responses, so that books are looked up again. The new responses are
+    /// still cached.
+    pub refresh: bool,
+}
+
+impl ResponseCache {
+    pub fn new(database_path: PathBuf, refresh: bool) -> ResponseCache {
+        ResponseCache {
+            database_path,
+            refresh,
+        }
+    }
+
+    pub fn get(&self, provider: &str, isbn: &str) -> Result<Option<String>> {
+        if self.refresh {
+            return Ok(None);
+        }
+        crate::db::get_cached_response(self.database_path.clone(), provider, isbn)
+    }
+
+    pub fn save(&self, provider: &str, isbn: &str, response: &str) -> Result<()> {
+        crate::db::save_cached_response(self.database_path.clone(), provider, isbn, response)
+    }
+
+    /// Delete every cached response, returning the number deleted.
+    pub fn clear(&self) -> Result<usize> {
+        crate::db::clear_cache(self.database_path.clone())
+    }
+
+    pub fn stats(&self) -> Result<Vec<CacheStats>> {
+        crate::db::get_cache_stats(self.database_path.clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ResponseCache;
+    use assert_fs::prelude::*;
+    use color_eyre::Result;
+
+    #[test]
+    fn get_should_return_a_saved_response_unless_refreshing() -> Result<()> {
+        let storage_dir = assert_fs::TempDir::new().unwrap();
+        let books_db_file = storage_dir.child("books.db");
+        let cache = ResponseCache::new(books_db_file.to_path_buf(), false);
+
+        assert_eq!(cache.get("ISBNdb", "9780233050485")?, None);
+        cache.save("ISBNdb", "9780233050485", "first")?;
+        cache.save("ISBNdb", "9780233050485", "second")?;
+        assert_eq!(
+            cache.get("ISBNdb", "9780233050485")?,
+            Some("second".to_string())
+        );
+        assert_eq!(cache.get("Open Library", "9780233050485")?, None);
+
+        let refreshing = ResponseCache::new(books_db_file.to_path_buf(), true);
+        assert_eq!(refreshing.get("ISBNdb", "9780233050485")?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn stats_and_clear_should_cover_every_provider() -> Result<()> {
+        let storage_dir = assert_fs::TempDir::new().unwrap();
+        let books_db_file = storage_dir.child("books.db");
+        let cache = ResponseCache::new(books_db_file.to_path_buf(), false);
+        cache.save("ISBNdb", "9780233050485", "12345")?;
+        cache.save("ISBNdb", "9780517597675", "123")?;
+        cache.save("Open Library", "9780517597675", "1")?;
+
+        let stats = cache.stats()?;
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].provider, "ISBNdb");
+        assert_eq!(stats[0].entries, 2);
+        assert_eq!(stats[0].size, 8);
+        assert_eq!(stats[1].provider, "Open Library");
+        assert_eq!(stats[1].entries, 1);
+
+        assert_eq!(cache.clear()?, 3);
+        assert!(cache.stats()?.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,403 @@
+use crate::books::Book;
+use crate::error::Result;
+use clap::ValueEnum;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::Write;
+
+/// Words skipped when choosing the title word for a citation key.
+const KEY_STOP_WORDS: [&str; 3] = ["a", "an", "the"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum CiteFormat {
+    Bibtex,
+    Ris,
+    /// The JSON used by Citation Style Language processors, such as Zotero and Pandoc
+    CslJson,
+}
+
+/// The citation key for a book: the first author's surname, the year of publication and the
+/// first significant word of the title, such as `dwyer1994two`.
+///
+/// Letters are folded to lowercase ASCII so the key is accepted by every format. Missing parts
+/// are left out.
+pub fn citation_key(book: &Book) -> String {
+    let surname = book
+        .authors
+        .first()
+        .map(|author| key_part(&author.surname))
+        .unwrap_or_default();
+    let year = book.year_published().unwrap_or_default();
+    let word = book
+        .title
+        .split_whitespace()
+        .map(key_part)
+        .find(|word| !word.is_empty() && !KEY_STOP_WORDS.contains(&word.as_str()))
+        .unwrap_or_default();
+    let key = format!("{surname}{year}{word}");
+    if key.is_empty() {
+        format!("book{}", book.id)
+    } else {
+        key
+    }
+}
+
+/// The citation keys for the books, in the same order.
+///
+/// Books in the library that would share a key are told apart with a suffix, in the order of
+/// their IDs, so the first is `dwyer1994two`, the second `dwyer1994twob`, and so on. The library
+/// should be every book in the database, so a book has the same key however it's cited.
+pub fn citation_keys(books: &[Book], library: &[Book]) -> Vec<String> {
+    let mut library: Vec<&Book> = library.iter().collect();
+    library.sort_by_key(|book| book.id);
+    let mut ids_by_key: HashMap<String, Vec<u32>> = HashMap::new();
+    for book in library {
+        ids_by_key
+            .entry(citation_key(book))
+            .or_default()
+            .push(book.id);
+    }
+    books
+        .iter()
+        .map(|book| {
+            let key = citation_key(book);
+            let position = ids_by_key
+                .get(&key)
+                .and_then(|ids| ids.iter().position(|id| *id == book.id))
+                .unwrap_or(0);
+            match position {
+                0 => key,
+                n => format!("{key}{}", suffix(n as u32 + 1)),
+            }
+        })
+        .collect()
+}
+
+/// Write citations for the books, with keys made unique across the library.
+pub fn write_citations<W: Write>(
+    books: &[Book],
+    library: &[Book],
+    format: CiteFormat,
+    mut writer: W,
+) -> Result<()> {
+    let keys = citation_keys(books, library);
+    match format {
+        CiteFormat::Bibtex => {
+            let entries: Vec<String> = books
+                .iter()
+                .zip(keys.iter())
+                .map(|(book, key)| to_bibtex(book, key))
+                .collect();
+            write!(writer, "{}", entries.join("\n"))?;
+        }
+        CiteFormat::Ris => {
+            for (book, key) in books.iter().zip(keys.iter()) {
+                write!(writer, "{}", to_ris(book, key))?;
+            }
+        }
+        CiteFormat::CslJson => {
+            let items: Vec<Value> = books
+                .iter()
+                .zip(keys.iter())
+                .map(|(book, key)| to_csl_json(book, key))
+                .collect();
+            serde_json::to_writer_pretty(&mut writer, &items)?;
+            writeln!(writer)?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn to_bibtex(book: &Book, key: &str) -> String {
+    let mut fields = Vec::new();
+    if !book.authors.is_empty() {
+        let authors: Vec<String> = book.authors.iter().map(|a| a.to_string()).collect();
+        fields.push(("author", authors.join(" and ")));
+    }
+    fields.push(("title", book.title.clone()));
+    fields.push(("publisher", book.publisher.name.clone()));
+    fields.push(("edition", book.edition.clone()));
+    if let Some(year) = book.year_published() {
+        fields.push(("year", year));
+    }
+    if let Some(isbn) = &book.isbn {
+        fields.push(("isbn", isbn.clone()));
+    }
+
+    let mut entry = format!("@book{{{key},\n");
+    for (name, value) in fields.iter().filter(|(_, value)| !value.is_empty()) {
+        entry.push_str(&format!("  {name} = {{{}}},\n", escape_bibtex(value)));
+    }
+    entry.push_str("}\n");
+    entry
+}
+
+fn to_ris(book: &Book, key: &str) -> String {
+    let mut lines = vec![("TY", "BOOK".to_string()), ("ID", key.to_string())];
+    lines.extend(book.authors.iter().map(|a| ("AU", a.to_string())));
+    lines.push(("TI", book.title.clone()));
+    lines.push(("PB", book.publisher.name.clone()));
+    lines.push(("ET", book.edition.clone()));
+    if let Some(year) = book.year_published() {
+        lines.push(("PY", year));
+    }
+    if let Some(isbn) = &book.isbn {
+        lines.push(("SN", isbn.clone()));
+    }
+    lines.push(("ER", String::new()));
+
+    // Each tag is followed by two spaces and a hyphen, and lines end with CR LF. A value can't
+    // span lines, so any line breaks are collapsed.
+    lines
+        .iter()
+        .filter(|(tag, value)| *tag == "ER" || !value.is_empty())
+        .map(|(tag, value)| {
+            let value = value.split_whitespace().collect::<Vec<&str>>().join(" ");
+            format!("{tag}  - {value}\r\n")
+        })
+        .collect()
+}
+
+fn to_csl_json(book: &Book, key: &str) -> Value {
+    let mut item = json!({
+        "id": key,
+        "type": "book",
+        "title": book.title,
+        "author": book
+            .authors
+            .iter()
+            .map(|author| {
+                if author.forename.is_empty() {
+                    json!({ "literal": author.surname })
+                } else {
+                    json!({ "family": author.surname, "given": author.forename })
+                }
+            })
+            .collect::<Vec<Value>>(),
+    });
+    let optional = [
+        ("publisher", book.publisher.name.as_str()),
+        ("edition", book.edition.as_str()),
+        ("ISBN", book.isbn.as_deref().unwrap_or_default()),
+    ];
+    for (name, value) in optional.iter().filter(|(_, value)| !value.is_empty()) {
+        item[*name] = json!(value);
+    }
+    if let Some(year) = book.year_published() {
+        item["issued"] = json!({ "date-parts": [[year.parse::<i32>().unwrap_or_default()]] });
+    }
+    item
+}
+
+/// Escape the characters that have a special meaning in BibTeX, so they appear as written.
+fn escape_bibtex(value: &str) -> String {
+    let mut escaped = String::new();
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\textbackslash{}"),
+            '~' => escaped.push_str("\\textasciitilde{}"),
+            '^' => escaped.push_str("\\textasciicircum{}"),
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' | '\r' => escaped.push(' '),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn key_part(value: &str) -> String {
+    value
+        .chars()
+        .flat_map(char::to_lowercase)
+        .map(fold_to_ascii)
+        .filter(|part| part.chars().all(|c| c.is_ascii_alphanumeric()))
+        .collect()
+}
+
+fn fold_to_ascii(c: char) -> String {
+    let folded = match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' => "a",
+        'æ' => "ae",
+        'ç' => "c",
+        'è' | 'é' | 'ê' | 'ë' => "e",
+        'ì' | 'í' | 'î' | 'ï' => "i",
+        'ñ' => "n",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' => "o",
+        'ß' => "ss",
+        'ù' | 'ú' | 'û' | 'ü' => "u",
+        'ý' | 'ÿ' => "y",
+        c => return c.to_string(),
+    };
+    folded.to_string()
+}
+
+/// The suffix for the nth book with the same key: b for the second, c for the third, and so on.
+fn suffix(n: u32) -> String {
+    let mut n = n - 1;
+    let mut suffix = Vec::new();
+    loop {
+        suffix.push((b'a' + (n % 26) as u8) as char);
+        n /= 26;
+        if n == 0 {
+            break;
+        }
+        n -= 1;
+    }
+    suffix.iter().rev().collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{citation_key, citation_keys, write_citations, CiteFormat};
+    use crate::books::{Author, Book, Publisher};
+
+    fn author(forename: &str, surname: &str) -> Author {
+        Author {
+            id: 1,
+            forename: forename.to_string(),
+            surname: surname.to_string(),
+        }
+    }
+
+    fn book(authors: Vec<Author>, title: &str, date_published: &str) -> Book {
+        Book {
+            id: 1,
+            authors,
+            publisher: Publisher {
+                id: 1,
+                name: "Crown".to_string(),
+            },
+            title: title.to_string(),
+            edition: "1st".to_string(),
+            date_published: date_published.to_string(),
+            binding: "Hardcover".to_string(),
+            isbn: Some("9780517597675".to_string()),
+            pages: 322,
+            owned: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn citation_key_should_use_surname_year_and_title_word() {
+        let two_seconds = book(
+            vec![author("Jim", "Dwyer"), author("Peg", "Tyre")],
+            "Two Seconds Under the World",
+            "1994",
+        );
+        assert_eq!(citation_key(&two_seconds), "dwyer1994two");
+
+        let odyssey = book(vec![author("", "Homer")], "The Odyssey", "1996-05-01");
+        assert_eq!(citation_key(&odyssey), "homer1996odyssey");
+
+        let accented = book(vec![author("Gabriel", "García Márquez")], "¡Él!", "");
+        assert_eq!(citation_key(&accented), "garciamarquezel");
+
+        let anonymous = book(Vec::new(), "", "");
+        assert_eq!(citation_key(&anonymous), "book1");
+    }
+
+    #[test]
+    fn citation_keys_should_tell_apart_books_with_the_same_key_by_id() {
+        let mut books = vec![
+            book(vec![author("Jim", "Dwyer")], "Two Seconds", "1994"),
+            book(vec![author("Jim", "Dwyer")], "Two Seconds", "1994"),
+            book(vec![author("Jim", "Dwyer")], "Two Seconds", "1994"),
+            book(vec![author("", "Homer")], "The Odyssey", "1996"),
+        ];
+        for (book, id) in books.iter_mut().zip([3, 1, 2, 4]) {
+            book.id = id;
+        }
+        assert_eq!(
+            citation_keys(&books, &books),
+            vec![
+                "dwyer1994twoc",
+                "dwyer1994two",
+                "dwyer1994twob",
+                "homer1996odyssey"
+            ]
+        );
+    }
+
+    #[test]
+    fn citation_keys_should_be_the_same_for_a_book_cited_alone() {
+        let mut library = vec![
+            book(vec![author("Jim", "Dwyer")], "Two Seconds", "1994"),
+            book(vec![author("Jim", "Dwyer")], "Two Seconds", "1994"),
+        ];
+        library[1].id = 2;
+        let all = citation_keys(&library, &library);
+        let alone = citation_keys(&library[1..], &library);
+        assert_eq!(alone, vec!["dwyer1994twob"]);
+        assert_eq!(alone[0], all[1]);
+    }
+
+    #[test]
+    fn bibtex_should_escape_special_characters() {
+        let mut books = vec![book(
+            vec![author("Jim", "Dwyer"), author("Peg", "Tyre")],
+            "100% Terror & the {Conspiracy}",
+            "1994",
+        )];
+        books[0].publisher.name = "Smith_Jones".to_string();
+        let mut output = Vec::new();
+        write_citations(&books, &books, CiteFormat::Bibtex, &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "@book{dwyer1994100,\n\
+             \x20 author = {Dwyer, Jim and Tyre, Peg},\n\
+             \x20 title = {100\\% Terror \\& the \\{Conspiracy\\}},\n\
+             \x20 publisher = {Smith\\_Jones},\n\
+             \x20 edition = {1st},\n\
+             \x20 year = {1994},\n\
+             \x20 isbn = {9780517597675},\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn ris_should_have_one_line_per_tag() {
+        let books = vec![book(
+            vec![author("Jim", "Dwyer"), author("Peg", "Tyre")],
+            "Two Seconds\nUnder the World",
+            "1994",
+        )];
+        let mut output = Vec::new();
+        write_citations(&books, &books, CiteFormat::Ris, &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "TY  - BOOK\r\nID  - dwyer1994two\r\nAU  - Dwyer, Jim\r\nAU  - Tyre, Peg\r\n\
+             TI  - Two Seconds Under the World\r\nPB  - Crown\r\nET  - 1st\r\nPY  - 1994\r\n\
+             SN  - 9780517597675\r\nER  - \r\n"
+        );
+    }
+
+    #[test]
+    fn csl_json_should_have_structured_names_and_dates() {
+        let books = vec![book(
+            vec![author("Jim", "Dwyer"), author("", "Homer")],
+            "Two \"Seconds\"",
+            "1994",
+        )];
+        let mut output = Vec::new();
+        write_citations(&books, &books, CiteFormat::CslJson, &mut output).unwrap();
+        let items: serde_json::Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(
+            items,
+            serde_json::json!([{
+                "id": "dwyer1994two",
+                "type": "book",
+                "title": "Two \"Seconds\"",
+                "author": [{ "family": "Dwyer", "given": "Jim" }, { "literal": "Homer" }],
+                "publisher": "Crown",
+                "edition": "1st",
+                "ISBN": "9780517597675",
+                "issued": { "date-parts": [[1994]] }
+            }])
+        );
+    }
+}
@@ -0,0 +1,101 @@
+use crate::error::{Error, Result};
+use crate::metadata::ProviderKind;
+use serde_derive::Deserialize;
+use std::path::Path;
+
+pub const CONFIG_FILE_NAME: &str = "config.toml";
+
+/// Settings read from the `config.toml` file in the storage directory.
+///
+/// The file is optional, as is every setting in it. Command line arguments take precedence.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The metadata provider used to look up books
+    pub provider: Option<ProviderKind>,
+    /// Settings for the ISBNdb provider, given in an `[isbndb]` table
+    pub isbndb: IsbnDbConfig,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IsbnDbConfig {
+    /// The most requests to send each second, which should match your ISBNdb plan
+    pub requests_per_second: f64,
+    /// The most ISBNs to look up with each request to the bulk endpoint
+    pub bulk_limit: usize,
+}
+
+impl Default for IsbnDbConfig {
+    /// The limit of ISBNdb's basic plan.
+    fn default() -> Self {
+        IsbnDbConfig {
+            requests_per_second: 1.0,
+            bulk_limit: crate::isbn_db::DEFAULT_BULK_LIMIT,
+        }
+    }
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Config> {
+        if !path.exists() {
+            return Ok(Config::default());
+        }
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| Error::ConfigError(format!("{}: {e}", path.display())))?;
+        contents.parse()
+    }
+}
+
+impl std::str::FromStr for Config {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        toml::from_str(s).map_err(|e| Error::ConfigError(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Config, CONFIG_FILE_NAME};
+    use crate::metadata::ProviderKind;
+    use assert_fs::prelude::*;
+    use color_eyre::Result;
+
+    #[test]
+    fn load_should_return_the_default_config_when_there_is_no_file() -> Result<()> {
+        let storage_dir = assert_fs::TempDir::new()?;
+        let config = Config::load(storage_dir.child(CONFIG_FILE_NAME).path())?;
+        assert_eq!(config.provider, None);
+        assert_eq!(config.isbndb.requests_per_second, 1.0);
+        Ok(())
+    }
+
+    #[test]
+    fn load_should_read_the_provider_setting() -> Result<()> {
+        let storage_dir = assert_fs::TempDir::new()?;
+        let config_file = storage_dir.child(CONFIG_FILE_NAME);
+        config_file.write_str("provider = \"isbndb\"\n")?;
+
+        let config = Config::load(config_file.path())?;
+
+        assert_eq!(config.provider, Some(ProviderKind::IsbnDb));
+        Ok(())
+    }
+
+    #[test]
+    fn parse_should_read_the_isbndb_settings() -> Result<()> {
+        let config: Config = "[isbndb]\nrequests_per_second = 3\nbulk_limit = 1000\n".parse()?;
+        assert_eq!(config.isbndb.requests_per_second, 3.0);
+        assert_eq!(config.isbndb.bulk_limit, 1000);
+        assert_eq!(config.provider, None);
+        Ok(())
+    }
+
+    #[test]
+    fn parse_should_reject_an_unknown_provider() {
+        assert!("provider = \"library-of-alexandria\""
+            .parse::<Config>()
+            .is_err());
+    }
+}
@@ -0,0 +1,366 @@
+use crate::books::{Book, BookRepository};
+use crate::error::{Error, Result};
+use reqwest::header::CONTENT_TYPE;
+use reqwest::Client;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// The directory, alongside the database, where the cover images are kept.
+pub const COVERS_DIR: &str = "covers";
+
+/// How long to wait for a cover, including reading the image, before giving up on it.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The largest image that will be saved. Covers are rarely more than a few hundred kilobytes.
+const DEFAULT_MAX_SIZE: u64 = 10 * 1024 * 1024;
+
+/// A cover image that has been downloaded for a book.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cover {
+    pub path: PathBuf,
+    /// The SHA-256 hash of the image, in hex
+    pub hash: String,
+}
+
+impl Cover {
+    /// The name of the image file, which is also used for the copy on the website.
+    pub fn file_name(&self) -> Option<&str> {
+        self.path.file_name().and_then(|name| name.to_str())
+    }
+}
+
+/// Downloads cover images into the covers directory.
+///
+/// Images are named by their hash, so a cover shared by several books is only stored once.
+pub struct CoverStore {
+    pub covers_dir: PathBuf,
+    client: Client,
+    timeout: Duration,
+    max_size: u64,
+}
+
+impl CoverStore {
+    pub fn new(storage_path: &Path) -> CoverStore {
+        CoverStore {
+            covers_dir: storage_path.join(COVERS_DIR),
+            client: Client::new(),
+            timeout: DEFAULT_TIMEOUT,
+            max_size: DEFAULT_MAX_SIZE,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn with_timeout(mut self, timeout: Duration) -> CoverStore {
+        self.timeout = timeout;
+        self
+    }
+
+    #[cfg(test)]
+    pub fn with_max_size(mut self, max_size: u64) -> CoverStore {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Download the image at the URL and save it in the covers directory.
+    ///
+    /// The download is abandoned if it takes too long or the image is too large, so a slow or
+    /// misbehaving server can't hold up the rest of the covers or fill the disk.
+    pub async fn download(&self, url: &str) -> Result<Cover> {
+        let failed = |reason: String| Error::CoverDownloadFailed {
+            url: url.to_string(),
+            reason,
+        };
+        let timed_out = |e: reqwest::Error| {
+            if e.is_timeout() {
+                failed(format!("it took longer than {:?}", self.timeout))
+            } else {
+                e.into()
+            }
+        };
+        let too_large = || failed(format!("the image is larger than {} bytes", self.max_size));
+        let mut response = self
+            .client
+            .get(url)
+            .timeout(self.timeout)
+            .send()
+            .await
+            .map_err(timed_out)?;
+        if !response.status().is_success() {
+            return Err(failed(format!("the server returned {}", response.status())));
+        }
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        if !content_type.is_empty() && !content_type.starts_with("image/") {
+            return Err(failed(format!(
+                "the response was {content_type}, not an image"
+            )));
+        }
+        if response
+            .content_length()
+            .is_some_and(|length| length > self.max_size)
+        {
+            return Err(too_large());
+        }
+        // The length isn't always given, so the image is read a chunk at a time.
+        let mut bytes = Vec::new();
+        while let Some(chunk) = response.chunk().await.map_err(timed_out)? {
+            if (bytes.len() + chunk.len()) as u64 > self.max_size {
+                return Err(too_large());
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+        if bytes.is_empty() {
+            return Err(failed("the response was empty".to_string()));
+        }
+
+        let hash = Sha256::digest(&bytes)
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>();
+        let path = self
+            .covers_dir
+            .join(format!("{hash}.{}", extension(&content_type, url)));
+        std::fs::create_dir_all(&self.covers_dir)?;
+        std::fs::write(&path, &bytes)?;
+        Ok(Cover { path, hash })
+    }
+}
+
+/// The file extension for the image, from its content type or else the URL. Most covers are
+/// JPEGs, so that's assumed when neither helps.
+fn extension(content_type: &str, url: &str) -> &'static str {
+    let from_url = url
+        .split(['?', '#'])
+        .next()
+        .and_then(|path| path.rsplit_once('.'))
+        .map(|(_, extension)| extension.to_lowercase());
+    // Any parameters, such as "image/png; qs=0.8", are ignored.
+    match content_type.split(';').next().unwrap_or_default().trim() {
+        "image/jpeg" | "image/jpg" => "jpg",
+        "image/png" => "png",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        _ => match from_url.as_deref() {
+            Some("png") => "png",
+            Some("gif") => "gif",
+            Some("webp") => "webp",
+            _ => "jpg",
+        },
+    }
+}
+
+/// Whether a book has an image URL but no cover on disk, either because it was never downloaded
+/// or because the file has since been deleted.
+pub fn is_missing(book: &Book) -> bool {
+    book.image_url.is_some()
+        && !book
+            .cover
+            .as_ref()
+            .is_some_and(|cover| cover.path.is_file())
+}
+
+/// Download the covers for the books and record them in the database.
+///
+/// Books without an image URL are left out. Each book's title is paired with its cover, or the
+/// reason it couldn't be downloaded, so one failure doesn't stop the rest.
+pub async fn fetch_covers(
+    books: &[Book],
+    store: &CoverStore,
+    book_repo: &BookRepository,
+) -> Vec<(String, Result<Cover>)> {
+    let mut results = Vec::new();
+    for book in books.iter() {
+        let Some(url) = &book.image_url else {
+            continue;
+        };
+        let cover = match store.download(url).await {
+            Ok(cover) => book_repo.set_cover(book.id, &cover).map(|_| cover),
+            Err(e) => Err(e),
+        };
+        results.push((book.title.clone(), cover));
+    }
+    results
+}
+
+#[cfg(test)]
+mod test {
+    use super::{extension, fetch_covers, is_missing, CoverStore};
+    use crate::books::{BookFilter, BookRepository, SortBy};
+    use crate::error::Error;
+    use crate::models::AddBookModel;
+    use assert_fs::prelude::*;
+    use color_eyre::Result;
+    use httpmock::prelude::*;
+    use std::time::Duration;
+
+    const IMAGE: &[u8] = b"\xff\xd8\xff\xe0 not really a jpeg";
+    const IMAGE_HASH: &str = "5368e7b92b51f4d19d0319af90a37a3795fb9f0ac3c0a64edd06eb7792bd4f78";
+
+    fn model(title: &str, image_url: Option<String>) -> AddBookModel {
+        AddBookModel {
+            title: title.to_string(),
+            authors: "Reeve, Simon".to_string(),
+            publisher: "Carlton Publishing Group".to_string(),
+            image_url,
+            ..AddBookModel::blank(None)
+        }
+    }
+
+    #[tokio::test]
+    async fn download_should_save_the_image_named_by_its_hash() -> Result<()> {
+        let storage_dir = assert_fs::TempDir::new()?;
+        let server = MockServer::start();
+        let cover_mock = server.mock(|when, then| {
+            when.method(GET).path("/covers/04/85/9780233050485.jpg");
+            then.status(200)
+                .header("Content-Type", "image/jpeg")
+                .body(IMAGE);
+        });
+
+        let store = CoverStore::new(storage_dir.path());
+        let cover = store
+            .download(&server.url("/covers/04/85/9780233050485.jpg"))
+            .await?;
+
+        cover_mock.assert();
+        assert_eq!(cover.hash, IMAGE_HASH);
+        assert_eq!(
+            cover.path,
+            storage_dir
+                .path()
+                .join("covers")
+                .join(format!("{IMAGE_HASH}.jpg"))
+        );
+        assert_eq!(std::fs::read(&cover.path)?, IMAGE);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn download_should_reject_a_response_that_is_not_an_image() -> Result<()> {
+        let storage_dir = assert_fs::TempDir::new()?;
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(GET).path("/missing.jpg");
+            then.status(404);
+        });
+        server.mock(|when, then| {
+            when.method(GET).path("/page.jpg");
+            then.status(200)
+                .header("Content-Type", "text/html")
+                .body("<html></html>");
+        });
+
+        let store = CoverStore::new(storage_dir.path());
+        for path in ["/missing.jpg", "/page.jpg"] {
+            let result = store.download(&server.url(path)).await;
+            assert!(
+                matches!(result, Err(Error::CoverDownloadFailed { .. })),
+                "{path}"
+            );
+        }
+        storage_dir
+            .child("covers")
+            .assert(predicates::path::missing());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn download_should_give_up_on_an_image_that_is_too_large_or_too_slow() -> Result<()> {
+        let storage_dir = assert_fs::TempDir::new()?;
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(GET).path("/large.jpg");
+            then.status(200)
+                .header("Content-Type", "image/jpeg")
+                .body(IMAGE);
+        });
+        server.mock(|when, then| {
+            when.method(GET).path("/slow.jpg");
+            then.status(200)
+                .header("Content-Type", "image/jpeg")
+                .body(IMAGE)
+                .delay(Duration::from_secs(2));
+        });
+
+        let store = CoverStore::new(storage_dir.path())
+            .with_max_size(IMAGE.len() as u64 - 1)
+            .with_timeout(Duration::from_millis(200));
+        for (path, expected) in [("/large.jpg", "larger than"), ("/slow.jpg", "longer than")] {
+            let result = store.download(&server.url(path)).await;
+            assert!(
+                matches!(&result, Err(Error::CoverDownloadFailed { reason, .. }) if reason.contains(expected)),
+                "{path}: {result:?}"
+            );
+        }
+        storage_dir
+            .child("covers")
+            .assert(predicates::path::missing());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn fetch_covers_should_record_the_cover_for_books_that_are_missing_one() -> Result<()> {
+        let storage_dir = assert_fs::TempDir::new()?;
+        let book_repo = BookRepository::new(storage_dir.child("books.db").to_path_buf());
+        let server = MockServer::start();
+        let cover_mock = server.mock(|when, then| {
+            when.method(GET).path("/cover.png");
+            then.status(200)
+                .header("Content-Type", "image/png")
+                .body(IMAGE);
+        });
+        book_repo.add_book(model("The New Jackals", Some(server.url("/cover.png"))))?;
+        book_repo.add_book(model("No Cover", None))?;
+
+        let books = book_repo.list(SortBy::Title, &BookFilter::default())?;
+        let missing: Vec<_> = books.into_iter().filter(is_missing).collect();
+        assert_eq!(missing.len(), 1);
+        let store = CoverStore::new(storage_dir.path());
+        let results = fetch_covers(&missing, &store, &book_repo).await;
+        assert_eq!(results.len(), 1);
+        assert!(results[0].1.is_ok());
+        cover_mock.assert();
+
+        let book = book_repo.get_by_id(missing[0].id)?;
+        let cover = book.cover.as_ref().unwrap();
+        assert_eq!(cover.hash, IMAGE_HASH);
+        assert_eq!(
+            cover.file_name(),
+            Some(format!("{IMAGE_HASH}.png").as_str())
+        );
+        assert!(!is_missing(&book));
+
+        // Editing the book keeps its cover.
+        book_repo.update_book(book.id, AddBookModel::from(book))?;
+        let book = book_repo.get_by_id(missing[0].id)?;
+        let cover = book.cover.as_ref().unwrap();
+        assert_eq!(cover.hash, IMAGE_HASH);
+
+        // Once the file is gone, the cover is missing again.
+        std::fs::remove_file(&cover.path)?;
+        assert!(is_missing(&book));
+        Ok(())
+    }
+
+    #[test]
+    fn extension_should_come_from_the_content_type_or_the_url() {
+        assert_eq!(extension("image/png", "https://example.com/cover"), "png");
+        assert_eq!(extension("", "https://example.com/cover.GIF?size=L"), "gif");
+        assert_eq!(extension("image/jpeg", "https://example.com/cover"), "jpg");
+        assert_eq!(
+            extension("image/jpeg", "https://example.com/cover.png"),
+            "jpg"
+        );
+        assert_eq!(extension("image/*", "https://example.com/cover.png"), "png");
+        assert_eq!(
+            extension("image/png; qs=0.8", "https://example.com/cover"),
+            "png"
+        );
+        assert_eq!(extension("", "https://example.com/content?id=1"), "jpg");
+    }
+}
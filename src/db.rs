@@ -1,119 +1,188 @@
-use crate::books::{Author, Book, Publisher};
+use crate::books::{Author, Book, Publisher, SearchResult, TagCount};
+use crate::cache::CacheStats;
+use crate::covers::Cover;
 use crate::error::{Error, Result};
-use rusqlite::{Connection, Result as RusqliteResult};
-use std::path::PathBuf;
+use rusqlite::{params, Connection, Result as RusqliteResult, Row};
+use std::path::{Path, PathBuf};
+
+/// Open a connection to the database, bringing its schema up to date, with foreign key
+/// constraints enforced.
+///
+/// SQLite doesn't enforce the constraints unless they are enabled for each connection. They are
+/// enabled after the migrations run, because some migrations need to rebuild tables.
+fn open(database_path: PathBuf) -> Result<Connection> {
+    let mut conn = Connection::open(database_path)?;
+    crate::migrations::migrate(&mut conn)?;
+    conn.pragma_update(None, "foreign_keys", "ON")?;
+    Ok(conn)
+}
 
 pub fn init_db(database_path: PathBuf) -> Result<()> {
-    let conn = Connection::open(database_path)?;
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS publishers (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            name TEXT NOT NULL UNIQUE
-         )",
-        [],
-    )?;
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS authors (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            forename TEXT NOT NULL,
-            surname TEXT NOT NULL,
-            UNIQUE(forename, surname)
-         )",
-        [],
-    )?;
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS books (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            publisher_id INTEGER,
-            title TEXT NOT NULL,
-            edition TEXT,
-            date_published TEXT NOT NULL,
-            original_date_published TEXT,
-            price DECIMAL,
-            binding TEXT NOT NULL,
-            isbn TEXT NOT NULL,
-            pages INTEGER NOT NULL DEFAULT 0,
-            owned INTEGER NOT NULL DEFAULT 0,
-            UNIQUE(title, edition),
-            FOREIGN KEY (publisher_id) REFERENCES publishers(id)
-         )",
-        [],
-    )?;
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS books_authors (
-            book_id INTEGER,
-            author_id INTEGER,
-            FOREIGN KEY (book_id) REFERENCES books(id),
-            FOREIGN KEY (author_id) REFERENCES authors(id)
-         )",
-        [],
-    )?;
+    open(database_path)?;
     Ok(())
 }
 
-#[allow(dead_code)]
+const SELECT_BOOKS: &str = "
+    SELECT
+        books.id, books.title, books.edition, books.date_published,
+        books.original_date_published, books.price, books.binding,
+        books.isbn, books.pages, books.owned,
+        books.rating, books.shelf, books.date_read,
+        books.short_title, books.synopsis, books.language,
+        books.dimensions, books.image_url, books.msrp,
+        books.isbn10, books.cover_path, books.cover_hash,
+        publishers.id, publishers.name
+    FROM books
+    LEFT JOIN publishers ON books.publisher_id = publishers.id
+";
+
 pub fn get_book(database_path: PathBuf, id: u32) -> Result<Book> {
-    let conn = Connection::open(database_path)?;
+    let conn = open(database_path.clone())?;
     let mut book = match conn.query_row(
-        "
-        SELECT 
-            books.id, books.title, books.edition, books.date_published,
-            books.original_date_published, books.price, books.binding, 
-            books.isbn, books.pages, books.owned, 
-            publishers.id, publishers.name
-        FROM books 
-        LEFT JOIN publishers ON books.publisher_id = publishers.id
-        WHERE books.id = ?1
-    ",
+        &format!("{SELECT_BOOKS} WHERE books.id = ?1"),
         [id],
-        |row| {
-            let book_id: u32 = row.get(0)?;
-            let title: String = row.get(1)?;
-            let edition: String = row.get(2)?;
-            let date_published: String = row.get(3)?;
-            let original_date_published: Option<String> = row.get(4)?;
-            let price: Option<f64> = row.get(5)?;
-            let binding: String = row.get(6)?;
-            let isbn: String = row.get(7)?;
-            let pages: u32 = row.get(8)?;
-            let owned: bool = row.get(9)?;
-            let publisher_id: u32 = row.get(10)?;
-            let publisher_name: String = row.get(11)?;
-
-            let publisher = Publisher {
-                id: publisher_id,
-                name: publisher_name,
-            };
-
-            Ok(Book {
-                id: book_id,
-                authors: vec![],
-                publisher,
-                title,
-                edition,
-                date_published,
-                original_date_published,
-                price,
-                binding,
-                isbn,
-                pages,
-                owned,
-            })
-        },
+        |row| book_from_row(row, storage_dir(&database_path)),
     ) {
         Ok(book) => book,
+        Err(rusqlite::Error::QueryReturnedNoRows) => {
+            return Err(Error::BookNotFound(format!("ID {id}")))
+        }
         Err(e) => return Err(Error::DatabaseError(e)),
     };
+    book.authors = get_book_authors(&conn, book.id)?;
+    book.tags = get_book_tags(&conn, book.id)?;
+    Ok(book)
+}
 
+pub fn get_book_by_isbn(database_path: PathBuf, isbn: &str) -> Result<Book> {
+    let conn = open(database_path.clone())?;
+    let mut book = match conn.query_row(
+        &format!("{SELECT_BOOKS} WHERE books.isbn = ?1"),
+        [isbn],
+        |row| book_from_row(row, storage_dir(&database_path)),
+    ) {
+        Ok(book) => book,
+        Err(rusqlite::Error::QueryReturnedNoRows) => {
+            return Err(Error::BookNotFound(format!("ISBN {isbn}")))
+        }
+        Err(e) => return Err(Error::DatabaseError(e)),
+    };
+    book.authors = get_book_authors(&conn, book.id)?;
+    book.tags = get_book_tags(&conn, book.id)?;
+    Ok(book)
+}
+
+/// Find the ID of the book with the given title and edition, which together are unique.
+pub fn get_book_id_by_title(
+    database_path: PathBuf,
+    title: &str,
+    edition: &str,
+) -> Result<Option<u32>> {
+    let conn = open(database_path)?;
+    match conn.query_row(
+        "SELECT id FROM books WHERE title = ?1 AND edition = ?2",
+        [title, edition],
+        |row| row.get(0),
+    ) {
+        Ok(id) => Ok(Some(id)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub fn get_books(database_path: PathBuf) -> Result<Vec<Book>> {
+    let conn = open(database_path.clone())?;
+    let mut stmt = conn.prepare(SELECT_BOOKS)?;
+    let book_rows = stmt.query_map([], |row| book_from_row(row, storage_dir(&database_path)))?;
+
+    let mut books = Vec::new();
+    for book_result in book_rows {
+        let mut book = book_result?;
+        book.authors = get_book_authors(&conn, book.id)?;
+        book.tags = get_book_tags(&conn, book.id)?;
+        books.push(book);
+    }
+    Ok(books)
+}
+
+/// The directory holding the database, which cover paths are relative to.
+fn storage_dir(database_path: &Path) -> &Path {
+    database_path.parent().unwrap_or(Path::new(""))
+}
+
+fn book_from_row(row: &Row, storage_dir: &Path) -> RusqliteResult<Book> {
+    let book_id: u32 = row.get(0)?;
+    let title: String = row.get(1)?;
+    let edition: String = row.get(2)?;
+    let date_published: String = row.get(3)?;
+    let original_date_published: Option<String> = row.get(4)?;
+    let price: Option<f64> = row.get(5)?;
+    let binding: String = row.get(6)?;
+    let isbn: Option<String> = row.get(7)?;
+    let pages: u32 = row.get(8)?;
+    let owned: bool = row.get(9)?;
+    let rating: Option<u8> = row.get(10)?;
+    let shelf: Option<String> = row.get(11)?;
+    let date_read: Option<String> = row.get(12)?;
+    let short_title: Option<String> = row.get(13)?;
+    let synopsis: Option<String> = row.get(14)?;
+    let language: Option<String> = row.get(15)?;
+    let dimensions: Option<String> = row.get(16)?;
+    let image_url: Option<String> = row.get(17)?;
+    let msrp: Option<f64> = row.get(18)?;
+    let isbn10: Option<String> = row.get(19)?;
+    let cover_path: Option<String> = row.get(20)?;
+    let cover_hash: Option<String> = row.get(21)?;
+    let publisher_id: u32 = row.get(22)?;
+    let publisher_name: String = row.get(23)?;
+
+    let publisher = Publisher {
+        id: publisher_id,
+        name: publisher_name,
+    };
+
+    Ok(Book {
+        id: book_id,
+        authors: vec![],
+        publisher,
+        title,
+        edition,
+        date_published,
+        original_date_published,
+        price,
+        binding,
+        isbn,
+        pages,
+        owned,
+        rating,
+        shelf,
+        date_read,
+        tags: vec![],
+        short_title,
+        synopsis,
+        language,
+        dimensions,
+        image_url,
+        msrp,
+        isbn10,
+        cover: cover_path.zip(cover_hash).map(|(path, hash)| Cover {
+            path: storage_dir.join(path),
+            hash,
+        }),
+    })
+}
+
+fn get_book_authors(conn: &Connection, book_id: u32) -> Result<Vec<Author>> {
     let mut stmt = conn.prepare(
         "
         SELECT authors.id, authors.forename, authors.surname
         FROM authors
         JOIN books_authors ON authors.id = books_authors.author_id
         WHERE books_authors.book_id = ?1
+        ORDER BY books_authors.rowid
     ",
     )?;
-    let author_rows: RusqliteResult<_> = stmt.query_map([id], |row| {
+    let author_rows = stmt.query_map([book_id], |row| {
         let id: u32 = row.get(0)?;
         let forename: String = row.get(1)?;
         let surname: String = row.get(2)?;
@@ -123,17 +192,145 @@ pub fn get_book(database_path: PathBuf, id: u32) -> Result<Book> {
             surname,
         };
         Ok(author)
-    });
+    })?;
 
-    for author_result in author_rows? {
-        book.authors.push(author_result?);
+    let mut authors = Vec::new();
+    for author_result in author_rows {
+        authors.push(author_result?);
     }
+    Ok(authors)
+}
 
-    Ok(book)
+fn get_book_tags(conn: &Connection, book_id: u32) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "
+        SELECT tags.name
+        FROM tags
+        JOIN books_tags ON tags.id = books_tags.tag_id
+        WHERE books_tags.book_id = ?1
+        ORDER BY books_tags.rowid
+    ",
+    )?;
+    let tag_rows = stmt.query_map([book_id], |row| row.get(0))?;
+
+    let mut tags = Vec::new();
+    for tag_result in tag_rows {
+        tags.push(tag_result?);
+    }
+    Ok(tags)
+}
+
+/// Link tags to a book, creating any that don't exist. Tags the book already has are ignored.
+fn save_book_tags(conn: &Connection, book_id: u32, tags: &[String]) -> Result<()> {
+    for tag in tags.iter() {
+        conn.execute("INSERT OR IGNORE INTO tags (name) VALUES (?1)", [tag])?;
+        conn.execute(
+            "INSERT OR IGNORE INTO books_tags (book_id, tag_id)
+            SELECT ?1, id FROM tags WHERE name = ?2",
+            (book_id, tag),
+        )?;
+    }
+    Ok(())
 }
 
-pub fn save_publisher(database_path: PathBuf, publisher: &Publisher) -> Result<u32> {
-    let conn = Connection::open(database_path)?;
+/// Delete tags that are not applied to any book.
+fn delete_orphan_tags(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "DELETE FROM tags WHERE id NOT IN (SELECT tag_id FROM books_tags)",
+        [],
+    )?;
+    Ok(())
+}
+
+pub fn add_book_tags(database_path: PathBuf, book_id: u32, tags: &[String]) -> Result<()> {
+    let mut conn = open(database_path)?;
+    let tx = conn.transaction()?;
+    save_book_tags(&tx, book_id, tags)?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Unlink tags from a book, deleting any that are no longer applied to a book.
+pub fn remove_book_tags(database_path: PathBuf, book_id: u32, tags: &[String]) -> Result<()> {
+    let mut conn = open(database_path)?;
+    let tx = conn.transaction()?;
+    for tag in tags.iter() {
+        tx.execute(
+            "DELETE FROM books_tags
+            WHERE book_id = ?1 AND tag_id IN (SELECT id FROM tags WHERE name = ?2)",
+            (book_id, tag),
+        )?;
+    }
+    delete_orphan_tags(&tx)?;
+    tx.commit()?;
+    Ok(())
+}
+
+pub fn get_tag_counts(database_path: PathBuf) -> Result<Vec<TagCount>> {
+    let conn = open(database_path)?;
+    let mut stmt = conn.prepare(
+        "
+        SELECT tags.name, COUNT(*)
+        FROM tags
+        JOIN books_tags ON tags.id = books_tags.tag_id
+        GROUP BY tags.id
+        ORDER BY tags.name
+    ",
+    )?;
+    let tag_rows = stmt.query_map([], |row| {
+        Ok(TagCount {
+            name: row.get(0)?,
+            books: row.get(1)?,
+        })
+    })?;
+
+    let mut tags = Vec::new();
+    for tag_result in tag_rows {
+        tags.push(tag_result?);
+    }
+    Ok(tags)
+}
+
+/// Search the full-text index, returning the matches with the best ranked first.
+///
+/// The query uses the FTS5 syntax. The matching terms in each result are wrapped in the `start`
+/// and `end` markers.
+pub fn search_books(
+    database_path: PathBuf,
+    query: &str,
+    start: &str,
+    end: &str,
+) -> Result<Vec<SearchResult>> {
+    let conn = open(database_path)?;
+    let mut stmt = conn.prepare(
+        "
+        SELECT
+            rowid,
+            highlight(books_fts, 0, ?2, ?3),
+            highlight(books_fts, 1, ?2, ?3),
+            highlight(books_fts, 2, ?2, ?3)
+        FROM books_fts
+        WHERE books_fts MATCH ?1
+        ORDER BY rank
+    ",
+    )?;
+    let result_rows = stmt.query_map((query, start, end), |row| {
+        Ok(SearchResult {
+            book_id: row.get(0)?,
+            title: row.get(1)?,
+            authors: row.get(2)?,
+            publisher: row.get(3)?,
+        })
+    })?;
+
+    let mut results = Vec::new();
+    for result in result_rows {
+        results.push(result?);
+    }
+    Ok(results)
+}
+
+fn insert_publisher(conn: &Connection, publisher: &Publisher) -> Result<u32> {
     conn.execute(
         "INSERT OR IGNORE INTO publishers (name) VALUES (?1)",
         [&publisher.name],
@@ -146,10 +343,9 @@ pub fn save_publisher(database_path: PathBuf, publisher: &Publisher) -> Result<u
     Ok(id)
 }
 
-pub fn save_author(database_path: PathBuf, author: &Author) -> Result<u32> {
-    let conn = Connection::open(database_path)?;
+fn insert_author(conn: &Connection, author: &Author) -> Result<u32> {
     conn.execute(
-        "INSERT INTO authors (forename, surname) VALUES (?1, ?2)",
+        "INSERT OR IGNORE INTO authors (forename, surname) VALUES (?1, ?2)",
         [&author.forename, &author.surname],
     )?;
     let id = conn.query_row(
@@ -160,15 +356,30 @@ pub fn save_author(database_path: PathBuf, author: &Author) -> Result<u32> {
     Ok(id)
 }
 
-pub fn save_book(database_path: PathBuf, book: &Book) -> Result<u32> {
-    let conn = Connection::open(database_path)?;
-    conn.execute(
+/// Save a new book, along with its publisher, authors and tags, and return its ID.
+///
+/// Everything is saved in one transaction, so a book that can't be saved doesn't leave behind a
+/// publisher or authors with no books.
+pub fn save_book(database_path: PathBuf, book: &mut Book) -> Result<u32> {
+    let mut conn = open(database_path)?;
+    let tx = conn.transaction()?;
+    book.publisher.id = insert_publisher(&tx, &book.publisher)?;
+    for author in book.authors.iter_mut() {
+        author.id = insert_author(&tx, author)?;
+    }
+    tx.execute(
         "INSERT INTO books (
             publisher_id, title, edition,
             date_published, original_date_published, price,
-            binding, isbn, pages, owned
-        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
-        (
+            binding, isbn, pages, owned,
+            rating, shelf, date_read,
+            short_title, synopsis, language,
+            dimensions, image_url, msrp, isbn10
+        ) VALUES (
+            ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10,
+            ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20
+        )",
+        params![
             book.publisher.id,
             &book.title,
             &book.edition,
@@ -179,22 +390,224 @@ pub fn save_book(database_path: PathBuf, book: &Book) -> Result<u32> {
             &book.isbn,
             &book.pages,
             &book.owned,
-        ),
+            &book.rating,
+            &book.shelf,
+            &book.date_read,
+            &book.short_title,
+            &book.synopsis,
+            &book.language,
+            &book.dimensions,
+            &book.image_url,
+            &book.msrp,
+            &book.isbn10,
+        ],
     )?;
-    let id = conn.query_row(
+    let id = tx.query_row(
         "SELECT id FROM books WHERE title = ?1 AND edition = ?2",
         [&book.title, &book.edition],
         |row| row.get(0),
     )?;
     for author in book.authors.iter() {
-        conn.execute(
+        tx.execute(
             "INSERT INTO books_authors (book_id, author_id) VALUES (?1, ?2)",
             (id, author.id),
         )?;
     }
+    save_book_tags(&tx, id, &book.tags)?;
+    tx.commit()?;
     Ok(id)
 }
 
+/// Update the details of a saved book, replacing its links to authors and tags.
+///
+/// The publisher and authors are saved if they're new, and their IDs are set on the book. This is
+/// all done in one transaction, so nothing is saved if the book doesn't exist. Any authors,
+/// publishers or tags that are no longer referenced by a book as a result of the update will be
+/// deleted. The cover is left as it is.
+pub fn update_book(database_path: PathBuf, book: &mut Book) -> Result<()> {
+    let mut conn = open(database_path)?;
+    let tx = conn.transaction()?;
+    book.publisher.id = insert_publisher(&tx, &book.publisher)?;
+    for author in book.authors.iter_mut() {
+        author.id = insert_author(&tx, author)?;
+    }
+    let updated = tx.execute(
+        "UPDATE books SET
+            publisher_id = ?1, title = ?2, edition = ?3,
+            date_published = ?4, original_date_published = ?5, price = ?6,
+            binding = ?7, isbn = ?8, pages = ?9, owned = ?10,
+            rating = ?11, shelf = ?12, date_read = ?13,
+            short_title = ?14, synopsis = ?15, language = ?16,
+            dimensions = ?17, image_url = ?18, msrp = ?19, isbn10 = ?20
+        WHERE id = ?21",
+        params![
+            book.publisher.id,
+            &book.title,
+            &book.edition,
+            &book.date_published,
+            &book.original_date_published,
+            &book.price,
+            &book.binding,
+            &book.isbn,
+            &book.pages,
+            &book.owned,
+            &book.rating,
+            &book.shelf,
+            &book.date_read,
+            &book.short_title,
+            &book.synopsis,
+            &book.language,
+            &book.dimensions,
+            &book.image_url,
+            &book.msrp,
+            &book.isbn10,
+            book.id,
+        ],
+    )?;
+    if updated == 0 {
+        return Err(Error::BookNotFound(format!("ID {}", book.id)));
+    }
+    tx.execute("DELETE FROM books_authors WHERE book_id = ?1", [book.id])?;
+    for author in book.authors.iter() {
+        tx.execute(
+            "INSERT INTO books_authors (book_id, author_id) VALUES (?1, ?2)",
+            (book.id, author.id),
+        )?;
+    }
+    tx.execute("DELETE FROM books_tags WHERE book_id = ?1", [book.id])?;
+    save_book_tags(&tx, book.id, &book.tags)?;
+    delete_orphans(&tx)?;
+    delete_orphan_tags(&tx)?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Record the cover image downloaded for a book.
+///
+/// The path is stored relative to the directory holding the database, so the directory can be
+/// moved without losing the covers.
+pub fn save_book_cover(database_path: PathBuf, id: u32, cover: &Cover) -> Result<()> {
+    let path = cover
+        .path
+        .strip_prefix(storage_dir(&database_path))
+        .unwrap_or(&cover.path)
+        .to_string_lossy()
+        .to_string();
+    let conn = open(database_path)?;
+    let updated = conn.execute(
+        "UPDATE books SET cover_path = ?1, cover_hash = ?2 WHERE id = ?3",
+        (&path, &cover.hash, id),
+    )?;
+    if updated == 0 {
+        return Err(Error::BookNotFound(format!("ID {id}")));
+    }
+    Ok(())
+}
+
+/// Delete a book along with its links to authors and tags.
+///
+/// Tags that are no longer applied to any book are deleted. If `remove_orphans` is set, any
+/// authors or publishers that no longer have any books as a result of the deletion are also
+/// deleted.
+pub fn delete_book(database_path: PathBuf, id: u32, remove_orphans: bool) -> Result<()> {
+    let mut conn = open(database_path)?;
+    let tx = conn.transaction()?;
+    tx.execute("DELETE FROM books_authors WHERE book_id = ?1", [id])?;
+    tx.execute("DELETE FROM books_tags WHERE book_id = ?1", [id])?;
+    let deleted = tx.execute("DELETE FROM books WHERE id = ?1", [id])?;
+    if deleted == 0 {
+        return Err(Error::BookNotFound(format!("ID {id}")));
+    }
+    delete_orphan_tags(&tx)?;
+    if remove_orphans {
+        delete_orphans(&tx)?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Delete authors and publishers that are not referenced by any book.
+fn delete_orphans(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "DELETE FROM authors WHERE id NOT IN (SELECT author_id FROM books_authors)",
+        [],
+    )?;
+    conn.execute(
+        "DELETE FROM publishers WHERE id NOT IN
+            (SELECT publisher_id FROM books WHERE publisher_id IS NOT NULL)",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Get the cached response from a provider for an ISBN, if there is one.
+pub fn get_cached_response(
+    database_path: PathBuf,
+    provider: &str,
+    isbn: &str,
+) -> Result<Option<String>> {
+    let conn = open(database_path)?;
+    match conn.query_row(
+        "SELECT response FROM provider_cache WHERE provider = ?1 AND isbn = ?2",
+        [provider, isbn],
+        |row| row.get(0),
+    ) {
+        Ok(response) => Ok(Some(response)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Save a response from a provider, replacing any earlier response for the same ISBN.
+pub fn save_cached_response(
+    database_path: PathBuf,
+    provider: &str,
+    isbn: &str,
+    response: &str,
+) -> Result<()> {
+    let conn = open(database_path)?;
+    conn.execute(
+        "INSERT OR REPLACE INTO provider_cache (provider, isbn, response, fetched_at)
+            VALUES (?1, ?2, ?3, CURRENT_TIMESTAMP)",
+        [provider, isbn, response],
+    )?;
+    Ok(())
+}
+
+/// Delete every cached response, returning the number deleted.
+pub fn clear_cache(database_path: PathBuf) -> Result<usize> {
+    let conn = open(database_path)?;
+    Ok(conn.execute("DELETE FROM provider_cache", [])?)
+}
+
+/// Summarise the cached responses for each provider.
+pub fn get_cache_stats(database_path: PathBuf) -> Result<Vec<CacheStats>> {
+    let conn = open(database_path)?;
+    let mut stmt = conn.prepare(
+        "
+        SELECT provider, COUNT(*), SUM(LENGTH(response)), MIN(fetched_at), MAX(fetched_at)
+        FROM provider_cache
+        GROUP BY provider
+        ORDER BY provider
+    ",
+    )?;
+    let stats_rows = stmt.query_map([], |row| {
+        Ok(CacheStats {
+            provider: row.get(0)?,
+            entries: row.get(1)?,
+            size: row.get(2)?,
+            oldest: row.get(3)?,
+            newest: row.get(4)?,
+        })
+    })?;
+
+    let mut stats = Vec::new();
+    for row in stats_rows {
+        stats.push(row?);
+    }
+    Ok(stats)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -278,4 +691,84 @@ mod test {
             .unwrap();
         assert!(rows.count() > 0)
     }
+
+    #[test]
+    fn init_db_should_create_the_search_index() {
+        let storage_dir = assert_fs::TempDir::new().unwrap();
+        let books_db_file = storage_dir.child("books.db");
+
+        init_db(books_db_file.to_path_buf()).unwrap();
+
+        let conn = Connection::open(books_db_file.path()).unwrap();
+        let mut statement = conn
+            .prepare("SELECT name FROM sqlite_master WHERE type='table' AND name=?")
+            .unwrap();
+        let rows = statement
+            .query_map(["books_fts"], |row| row.get::<_, String>(0))
+            .unwrap();
+        assert!(rows.count() > 0)
+    }
+
+    #[test]
+    fn search_index_should_be_kept_in_sync_with_authors_and_publishers() {
+        let storage_dir = assert_fs::TempDir::new().unwrap();
+        let books_db_file = storage_dir.child("books.db");
+        init_db(books_db_file.to_path_buf()).unwrap();
+
+        let conn = Connection::open(books_db_file.path()).unwrap();
+        conn.execute_batch(
+            "
+            INSERT INTO publishers (name) VALUES ('Crown');
+            INSERT INTO authors (forename, surname) VALUES ('Jim', 'Dwyer');
+            INSERT INTO books (publisher_id, title, edition, date_published, binding, isbn)
+            VALUES (1, 'Two Seconds Under the World', '1st', '1997', 'Hardcover', '9780517597675');
+            INSERT INTO books_authors (book_id, author_id) VALUES (1, 1);
+        ",
+        )
+        .unwrap();
+
+        let results = search_books(books_db_file.to_path_buf(), "dwyer", "[", "]").unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].authors, "Jim [Dwyer]");
+
+        conn.execute_batch(
+            "
+            UPDATE authors SET forename = 'James' WHERE id = 1;
+            UPDATE publishers SET name = 'Crown Publishers' WHERE id = 1;
+        ",
+        )
+        .unwrap();
+
+        let results = search_books(books_db_file.to_path_buf(), "james", "[", "]").unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].book_id, 1);
+        let results = search_books(books_db_file.to_path_buf(), "publishers", "[", "]").unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].publisher, "Crown [Publishers]");
+
+        conn.execute_batch(
+            "
+            DELETE FROM books_authors WHERE book_id = 1;
+            DELETE FROM books WHERE id = 1;
+        ",
+        )
+        .unwrap();
+
+        let results = search_books(books_db_file.to_path_buf(), "world", "[", "]").unwrap();
+        assert!(results.is_empty());
+    }
+
+    #[test]
+    fn connections_should_enforce_foreign_keys() {
+        let storage_dir = assert_fs::TempDir::new().unwrap();
+        let books_db_file = storage_dir.child("books.db");
+        init_db(books_db_file.to_path_buf()).unwrap();
+
+        let conn = open(books_db_file.to_path_buf()).unwrap();
+        let result = conn.execute(
+            "INSERT INTO books_authors (book_id, author_id) VALUES (?1, ?2)",
+            (1, 1),
+        );
+        assert!(result.is_err());
+    }
 }
@@ -3,11 +3,65 @@ use thiserror::Error;
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
 #[derive(Debug, Error)]
-#[allow(missing_docs)]
+#[allow(missing_docs, clippy::enum_variant_names)]
 pub enum Error {
     #[error(transparent)]
     DatabaseError(#[from] rusqlite::Error),
 
+    #[error(transparent)]
+    HttpError(#[from] reqwest::Error),
+
+    #[error(transparent)]
+    JsonError(#[from] serde_json::Error),
+
+    #[error(transparent)]
+    CsvError(#[from] csv::Error),
+
+    #[error(transparent)]
+    IoError(#[from] std::io::Error),
+
+    #[error(transparent)]
+    TemplateError(#[from] askama::Error),
+
+    #[error("Failed to read the configuration: {0}")]
+    ConfigError(String),
+
+    #[error("Failed to import: {0}")]
+    ImportError(String),
+
     #[error("Failed to parse string to model: {0}")]
     ParseError(String),
+
+    #[error("Invalid ISBN: {0}")]
+    InvalidIsbn(String),
+
+    #[error("There is no book with {0} in the database")]
+    BookNotFound(String),
+
+    #[error("{provider} has no record for ISBN {isbn}")]
+    RecordNotFound { provider: String, isbn: String },
+
+    #[error("{0} rejected the API key")]
+    Unauthorized(String),
+
+    #[error("The daily request quota for {0} has been used up; try again tomorrow")]
+    QuotaExceeded(String),
+
+    #[error("{0} is refusing requests for being over its rate limit; try again later")]
+    RateLimited(String),
+
+    #[error("Failed to download the cover from {url}: {reason}")]
+    CoverDownloadFailed { url: String, reason: String },
+
+    #[error("{provider} returned a response that could not be read: {reason}")]
+    MalformedResponse { provider: String, reason: String },
+
+    #[error(
+        "The database schema is at version {found}, but this version of books only supports up \
+         to version {supported}"
+    )]
+    SchemaTooNew { found: usize, supported: usize },
+
+    #[error("Failed to migrate the database schema to version {version}: {reason}")]
+    MigrationFailed { version: usize, reason: String },
 }
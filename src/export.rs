@@ -0,0 +1,335 @@
+use crate::books::{Book, BookFilter, BookRepository, SortBy};
+use crate::error::{Error, Result};
+use crate::models::AddBookModel;
+use clap::ValueEnum;
+use serde_derive::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+    /// MARC 21 records in the ISO 2709 exchange format
+    Marc21,
+    Marcxml,
+}
+
+/// An author as written to a JSON export.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExportedAuthor {
+    pub surname: String,
+    #[serde(default)]
+    pub forename: String,
+}
+
+/// A book as written to a JSON export, and read back by `import json`.
+///
+/// The ID is included for scripts, but it isn't kept when the book is imported.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExportedBook {
+    #[serde(default)]
+    pub id: u32,
+    pub title: String,
+    pub authors: Vec<ExportedAuthor>,
+    pub publisher: String,
+    #[serde(default)]
+    pub edition: String,
+    #[serde(default)]
+    pub date_published: String,
+    pub original_date_published: Option<String>,
+    pub price: Option<f64>,
+    #[serde(default)]
+    pub binding: String,
+    pub isbn: Option<String>,
+    #[serde(default)]
+    pub pages: u32,
+    #[serde(default)]
+    pub owned: bool,
+    pub rating: Option<u8>,
+    pub shelf: Option<String>,
+    pub date_read: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub short_title: Option<String>,
+    #[serde(default)]
+    pub synopsis: Option<String>,
+    #[serde(default)]
+    pub language: Option<String>,
+    #[serde(default)]
+    pub dimensions: Option<String>,
+    #[serde(default)]
+    pub image_url: Option<String>,
+    #[serde(default)]
+    pub msrp: Option<f64>,
+    #[serde(default)]
+    pub isbn10: Option<String>,
+    /// The path of the downloaded cover image, which isn't read back when importing
+    #[serde(default)]
+    pub cover: Option<String>,
+}
+
+impl From<&Book> for ExportedBook {
+    fn from(book: &Book) -> Self {
+        ExportedBook {
+            id: book.id,
+            title: book.title.clone(),
+            authors: book
+                .authors
+                .iter()
+                .map(|author| ExportedAuthor {
+                    surname: author.surname.clone(),
+                    forename: author.forename.clone(),
+                })
+                .collect(),
+            publisher: book.publisher.name.clone(),
+            edition: book.edition.clone(),
+            date_published: book.date_published.clone(),
+            original_date_published: book.original_date_published.clone(),
+            price: book.price,
+            binding: book.binding.clone(),
+            isbn: book.isbn.clone(),
+            pages: book.pages,
+            owned: book.owned,
+            rating: book.rating,
+            shelf: book.shelf.clone(),
+            date_read: book.date_read.clone(),
+            tags: book.tags.clone(),
+            short_title: book.short_title.clone(),
+            synopsis: book.synopsis.clone(),
+            language: book.language.clone(),
+            dimensions: book.dimensions.clone(),
+            image_url: book.image_url.clone(),
+            msrp: book.msrp,
+            isbn10: book.isbn10.clone(),
+            cover: cover_path(book),
+        }
+    }
+}
+
+impl From<ExportedBook> for AddBookModel {
+    fn from(book: ExportedBook) -> Self {
+        let authors = book
+            .authors
+            .iter()
+            .map(|author| {
+                if author.forename.is_empty() {
+                    author.surname.clone()
+                } else {
+                    format!("{}, {}", author.surname, author.forename)
+                }
+            })
+            .collect::<Vec<String>>()
+            .join("; ");
+        AddBookModel {
+            authors,
+            publisher: book.publisher,
+            title: book.title,
+            edition: book.edition,
+            date_published: book.date_published,
+            original_date_published: book.original_date_published,
+            price: book.price,
+            binding: book.binding,
+            isbn: book.isbn,
+            pages: book.pages,
+            owned: book.owned,
+            rating: book.rating,
+            shelf: book.shelf,
+            date_read: book.date_read,
+            tags: book.tags,
+            short_title: book.short_title,
+            synopsis: book.synopsis,
+            language: book.language,
+            dimensions: book.dimensions,
+            image_url: book.image_url,
+            msrp: book.msrp,
+            isbn10: book.isbn10,
+        }
+    }
+}
+
+/// A book as written to a CSV export, one column per field in this order.
+///
+/// The authors are given as "Surname, Forename", separated by semicolons, as are the tags.
+#[derive(Debug, Serialize)]
+struct CsvRow<'a> {
+    id: u32,
+    title: &'a str,
+    authors: String,
+    publisher: &'a str,
+    edition: &'a str,
+    date_published: &'a str,
+    original_date_published: Option<&'a str>,
+    price: Option<f64>,
+    binding: &'a str,
+    isbn: Option<&'a str>,
+    pages: u32,
+    owned: bool,
+    rating: Option<u8>,
+    shelf: Option<&'a str>,
+    date_read: Option<&'a str>,
+    tags: String,
+    short_title: Option<&'a str>,
+    synopsis: Option<&'a str>,
+    language: Option<&'a str>,
+    dimensions: Option<&'a str>,
+    image_url: Option<&'a str>,
+    msrp: Option<f64>,
+    isbn10: Option<&'a str>,
+    cover: Option<String>,
+}
+
+impl<'a> From<&'a Book> for CsvRow<'a> {
+    fn from(book: &'a Book) -> Self {
+        CsvRow {
+            id: book.id,
+            title: &book.title,
+            authors: book.authors_display(),
+            publisher: &book.publisher.name,
+            edition: &book.edition,
+            date_published: &book.date_published,
+            original_date_published: book.original_date_published.as_deref(),
+            price: book.price,
+            binding: &book.binding,
+            isbn: book.isbn.as_deref(),
+            pages: book.pages,
+            owned: book.owned,
+            rating: book.rating,
+            shelf: book.shelf.as_deref(),
+            date_read: book.date_read.as_deref(),
+            tags: book.tags.join("; "),
+            short_title: book.short_title.as_deref(),
+            synopsis: book.synopsis.as_deref(),
+            language: book.language.as_deref(),
+            dimensions: book.dimensions.as_deref(),
+            image_url: book.image_url.as_deref(),
+            msrp: book.msrp,
+            isbn10: book.isbn10.as_deref(),
+            cover: cover_path(book),
+        }
+    }
+}
+
+fn cover_path(book: &Book) -> Option<String> {
+    book.cover
+        .as_ref()
+        .map(|cover| cover.path.display().to_string())
+}
+
+/// Every book in the database, in the order they were added.
+pub fn books_to_export(book_repo: &BookRepository) -> Result<Vec<Book>> {
+    let mut books = book_repo.list(SortBy::Title, &BookFilter::default())?;
+    books.sort_by_key(|book| book.id);
+    Ok(books)
+}
+
+pub fn write_books<W: Write>(books: &[Book], format: ExportFormat, writer: W) -> Result<()> {
+    match format {
+        ExportFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(writer);
+            for book in books {
+                writer.serialize(CsvRow::from(book))?;
+            }
+            writer.flush()?;
+        }
+        ExportFormat::Json => {
+            let books: Vec<ExportedBook> = books.iter().map(ExportedBook::from).collect();
+            let mut writer = writer;
+            serde_json::to_writer_pretty(&mut writer, &books)?;
+            writeln!(writer)?;
+        }
+        ExportFormat::Marc21 => crate::marc::write_marc21(books, writer)?,
+        ExportFormat::Marcxml => crate::marc::write_marcxml(books, writer)?,
+    }
+    Ok(())
+}
+
+/// Read the books from a JSON export, paired with descriptions for the import report.
+pub fn read_json(path: &Path) -> Result<Vec<(String, Result<AddBookModel>)>> {
+    let file = std::fs::File::open(path)
+        .map_err(|e| Error::ImportError(format!("Could not read {}: {e}", path.display())))?;
+    let books: Vec<ExportedBook> = serde_json::from_reader(std::io::BufReader::new(file))?;
+    Ok(books
+        .into_iter()
+        .map(|book| {
+            let description = match &book.isbn {
+                Some(isbn) => format!("{} ({isbn})", book.title),
+                None => book.title.clone(),
+            };
+            (description, Ok(AddBookModel::from(book)))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{books_to_export, read_json, write_books, ExportFormat};
+    use crate::books::BookRepository;
+    use crate::goodreads::read_library_export;
+    use crate::import::import_models;
+    use assert_fs::prelude::*;
+    use color_eyre::Result;
+    use std::path::Path;
+
+    fn repo_with_books(storage_dir: &assert_fs::TempDir) -> Result<BookRepository> {
+        let book_repo = BookRepository::new(storage_dir.child("books.db").to_path_buf());
+        let export = Path::new("resources").join("goodreads_library_export.csv");
+        import_models(read_library_export(&export)?, &book_repo, false)?;
+        Ok(book_repo)
+    }
+
+    #[test]
+    fn csv_export_should_have_one_row_per_book_in_column_order() -> Result<()> {
+        let storage_dir = assert_fs::TempDir::new()?;
+        let book_repo = repo_with_books(&storage_dir)?;
+        let mut output = Vec::new();
+        write_books(
+            &books_to_export(&book_repo)?,
+            ExportFormat::Csv,
+            &mut output,
+        )?;
+        let output = String::from_utf8(output)?;
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(lines.len(), 4);
+        assert_eq!(
+            lines[0],
+            "id,title,authors,publisher,edition,date_published,original_date_published,price,\
+             binding,isbn,pages,owned,rating,shelf,date_read,tags,short_title,synopsis,language,\
+             dimensions,image_url,msrp,isbn10,cover"
+        );
+        assert_eq!(
+            lines[2],
+            "2,The New Jackals: Osama Bin Laden and the Future of Terrorism,\"Reeve, Simon\",\
+             Carlton Publishing Group,,2001,1999,,Paperback,9780233050485,352,false,,to-read,,,,,,,,,,"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn json_export_should_be_imported_without_losing_anything() -> Result<()> {
+        let storage_dir = assert_fs::TempDir::new()?;
+        let book_repo = repo_with_books(&storage_dir)?;
+        let exported = storage_dir.child("books.json");
+        let file = std::fs::File::create(exported.path())?;
+        write_books(&books_to_export(&book_repo)?, ExportFormat::Json, file)?;
+
+        let other_dir = assert_fs::TempDir::new()?;
+        let other_repo = BookRepository::new(other_dir.child("books.db").to_path_buf());
+        let report = import_models(read_json(exported.path())?, &other_repo, false)?;
+        assert_eq!(report.added.len(), 3);
+
+        let mut output = Vec::new();
+        write_books(
+            &books_to_export(&other_repo)?,
+            ExportFormat::Json,
+            &mut output,
+        )?;
+        assert_eq!(
+            std::fs::read_to_string(exported.path())?,
+            String::from_utf8(output)?
+        );
+        Ok(())
+    }
+}
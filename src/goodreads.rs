@@ -0,0 +1,292 @@
+use crate::error::{Error, Result};
+use crate::metadata::surname_first;
+use crate::models::{parse_rating, AddBookModel};
+use serde_derive::Deserialize;
+use std::path::Path;
+
+/// A row of the CSV file produced by Goodreads' "Export Library".
+///
+/// Only the columns that are imported are listed; the rest are ignored.
+#[derive(Debug, Deserialize)]
+pub struct GoodreadsRow {
+    #[serde(rename = "Title")]
+    pub title: String,
+    #[serde(rename = "Author")]
+    pub author: String,
+    /// The primary author as "Surname, Forename"
+    #[serde(rename = "Author l-f", default)]
+    pub author_lf: String,
+    #[serde(rename = "Additional Authors", default)]
+    pub additional_authors: String,
+    #[serde(rename = "ISBN", default)]
+    pub isbn: String,
+    #[serde(rename = "ISBN13", default)]
+    pub isbn13: String,
+    #[serde(rename = "My Rating", default)]
+    pub rating: String,
+    #[serde(rename = "Publisher", default)]
+    pub publisher: String,
+    #[serde(rename = "Binding", default)]
+    pub binding: String,
+    #[serde(rename = "Number of Pages", default)]
+    pub pages: String,
+    #[serde(rename = "Year Published", default)]
+    pub year_published: String,
+    #[serde(rename = "Original Publication Year", default)]
+    pub original_publication_year: String,
+    #[serde(rename = "Exclusive Shelf", default)]
+    pub shelf: String,
+    #[serde(rename = "Date Read", default)]
+    pub date_read: String,
+    #[serde(rename = "Owned Copies", default)]
+    pub owned_copies: String,
+}
+
+impl GoodreadsRow {
+    /// A description of the row for reports, using the ISBN when it has one.
+    pub fn describe(&self) -> String {
+        match unquote_isbn(&self.isbn13).or_else(|| unquote_isbn(&self.isbn)) {
+            Some(isbn) => format!("{} ({isbn})", self.title),
+            None => self.title.clone(),
+        }
+    }
+}
+
+impl TryFrom<GoodreadsRow> for AddBookModel {
+    type Error = Error;
+
+    fn try_from(row: GoodreadsRow) -> Result<Self, Self::Error> {
+        // Goodreads gives the primary author's surname first in its own column, but additional
+        // authors only as "Forename Surname", separated by commas, so their surnames are guessed.
+        // An author with a single name, such as Homer, is given as "Homer, ".
+        let author = match row.author_lf.trim().trim_end_matches(',') {
+            "" => surname_first(row.author.trim()),
+            author => author.to_string(),
+        };
+        let authors = std::iter::once(author)
+            .chain(
+                row.additional_authors
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .map(surname_first),
+            )
+            .filter(|name| !name.is_empty())
+            .collect::<Vec<String>>()
+            .join("; ");
+        let isbn = unquote_isbn(&row.isbn13).or_else(|| unquote_isbn(&row.isbn));
+        // An unrated book has a rating of 0.
+        let rating = match row.rating.trim() {
+            "" | "0" => None,
+            rating => Some(parse_rating(rating)?),
+        };
+        let pages = match row.pages.trim() {
+            "" => 0,
+            pages => pages.parse().map_err(|_| {
+                Error::ParseError(format!("Could not parse the number of pages {pages}"))
+            })?,
+        };
+        let owned_copies: u32 = row.owned_copies.trim().parse().unwrap_or(0);
+        Ok(AddBookModel {
+            authors,
+            publisher: row.publisher.trim().to_string(),
+            title: row.title.trim().to_string(),
+            edition: String::new(),
+            date_published: row.year_published.trim().to_string(),
+            original_date_published: non_empty(&row.original_publication_year),
+            price: None,
+            binding: row.binding.trim().to_string(),
+            isbn,
+            pages,
+            owned: owned_copies > 0,
+            rating,
+            shelf: non_empty(&row.shelf),
+            // Dates are given as 2021/03/14.
+            date_read: non_empty(&row.date_read).map(|date| date.replace('/', "-")),
+            tags: Vec::new(),
+            short_title: None,
+            synopsis: None,
+            language: None,
+            dimensions: None,
+            image_url: None,
+            msrp: None,
+            isbn10: None,
+        })
+    }
+}
+
+/// Read the books from a Goodreads library export.
+///
+/// Each book is paired with a description for the import report. Rows that can't be converted
+/// are returned as errors, so they can be reported without abandoning the rest of the file.
+pub fn read_library_export(path: &Path) -> Result<Vec<(String, Result<AddBookModel>)>> {
+    let mut reader = csv::Reader::from_path(path)?;
+    let mut models = Vec::new();
+    for (i, row) in reader.deserialize::<GoodreadsRow>().enumerate() {
+        let row = match row {
+            Ok(row) => row,
+            Err(e) => {
+                models.push((format!("Row {}", i + 1), Err(e.into())));
+                continue;
+            }
+        };
+        let description = row.describe();
+        models.push((description, AddBookModel::try_from(row)));
+    }
+    Ok(models)
+}
+
+/// Goodreads writes ISBNs as spreadsheet formulas, such as `="9780517597675"`, so they aren't
+/// treated as numbers. A book without an ISBN has `=""`.
+fn unquote_isbn(value: &str) -> Option<String> {
+    let value = value
+        .trim()
+        .trim_start_matches('=')
+        .trim_matches('"')
+        .trim();
+    non_empty(value)
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    let value = value.trim();
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{read_library_export, GoodreadsRow};
+    use crate::books::{BookFilter, BookRepository, SortBy};
+    use crate::import::import_models;
+    use crate::models::AddBookModel;
+    use assert_fs::prelude::*;
+    use color_eyre::Result;
+
+    fn export_path() -> std::path::PathBuf {
+        std::path::Path::new("resources").join("goodreads_library_export.csv")
+    }
+
+    #[test]
+    fn read_library_export_should_map_the_goodreads_columns() -> Result<()> {
+        let models = read_library_export(&export_path())?;
+        assert_eq!(models.len(), 4);
+
+        let (description, model) = &models[0];
+        let model = model.as_ref().unwrap();
+        assert_eq!(
+            *description,
+            "Two Seconds Under the World: Terror Comes to America-The Conspiracy Behind the World Trade Center Bombing (9780517597675)"
+        );
+        assert_eq!(
+            model.authors,
+            "Dwyer, Jim; Murphy, Deidre; Tyre, Peg; Kocieniewski, David"
+        );
+        assert_eq!(model.isbn, Some("9780517597675".to_string()));
+        assert_eq!(model.publisher, "Crown");
+        assert_eq!(model.binding, "Hardcover");
+        assert_eq!(model.pages, 322);
+        assert_eq!(model.date_published, "1994");
+        assert_eq!(model.original_date_published, Some("1994".to_string()));
+        assert_eq!(model.rating, Some(4));
+        assert_eq!(model.shelf, Some("read".to_string()));
+        assert_eq!(model.date_read, Some("2021-03-14".to_string()));
+        assert!(model.owned);
+
+        let model = models[1].1.as_ref().unwrap();
+        assert_eq!(model.authors, "Reeve, Simon");
+        assert_eq!(model.rating, None);
+        assert_eq!(model.shelf, Some("to-read".to_string()));
+        assert_eq!(model.date_read, None);
+        assert!(!model.owned);
+
+        let model = models[2].1.as_ref().unwrap();
+        assert_eq!(models[2].0, "The Odyssey");
+        assert_eq!(model.authors, "Homer");
+        assert_eq!(model.isbn, None);
+        assert_eq!(model.original_date_published, Some("-700".to_string()));
+        Ok(())
+    }
+
+    #[test]
+    fn try_from_should_take_the_primary_author_from_the_author_l_f_column() -> Result<()> {
+        let csv = "Title,Author,Author l-f,Additional Authors\n\
+                   The Dispossessed,Ursula K. Le Guin,\"Le Guin, Ursula K.\",Jim Dwyer\n";
+        let row: GoodreadsRow = csv::Reader::from_reader(csv.as_bytes())
+            .deserialize()
+            .next()
+            .unwrap()?;
+        let model = AddBookModel::try_from(row)?;
+        assert_eq!(model.authors, "Le Guin, Ursula K.; Dwyer, Jim");
+        Ok(())
+    }
+
+    #[test]
+    fn import_should_skip_another_edition_with_the_same_title() -> Result<()> {
+        let storage_dir = assert_fs::TempDir::new()?;
+        let book_repo = BookRepository::new(storage_dir.child("books.db").to_path_buf());
+        let export = storage_dir.child("goodreads_library_export.csv");
+        export.write_str(
+            "Title,Author,Author l-f,Additional Authors,ISBN13,Publisher\n\
+             The Odyssey,Homer,\"Homer, \",,=\"9780140268867\",Penguin Classics\n\
+             The Odyssey,Homer,\"Homer, \",Emily Wilson,=\"9780393089059\",Norton\n",
+        )?;
+
+        for dry_run in [true, false] {
+            let report = import_models(read_library_export(export.path())?, &book_repo, dry_run)?;
+            assert_eq!(report.added.len(), 1);
+            assert_eq!(report.skipped.len(), 1);
+            assert!(report.failed.is_empty());
+            assert_eq!(
+                report.skipped[0].1,
+                "Listed more than once with the same title and edition"
+            );
+        }
+
+        let report = import_models(read_library_export(export.path())?, &book_repo, false)?;
+        assert!(report.added.is_empty());
+        assert_eq!(
+            report.skipped[1],
+            (
+                "The Odyssey (9780393089059)".to_string(),
+                "The book with ID 1 has the same title and edition".to_string()
+            )
+        );
+        let books = book_repo.list(SortBy::Title, &BookFilter::default())?;
+        assert_eq!(books.len(), 1);
+        assert_eq!(books[0].publisher.name, "Penguin Classics");
+        Ok(())
+    }
+
+    #[test]
+    fn import_should_add_the_books_once_and_skip_duplicates() -> Result<()> {
+        let storage_dir = assert_fs::TempDir::new()?;
+        let book_repo = BookRepository::new(storage_dir.child("books.db").to_path_buf());
+
+        let preview = import_models(read_library_export(&export_path())?, &book_repo, true)?;
+        assert_eq!(preview.added.len(), 3);
+        assert!(preview.added_ids.is_empty());
+        assert_eq!(preview.skipped.len(), 1);
+        assert!(book_repo
+            .list(SortBy::Title, &BookFilter::default())?
+            .is_empty());
+
+        let report = import_models(read_library_export(&export_path())?, &book_repo, false)?;
+        assert_eq!(report.added.len(), 3);
+        assert_eq!(report.added_ids.len(), 3);
+        assert_eq!(report.skipped.len(), 1);
+        assert!(report.failed.is_empty());
+        let book = book_repo.get_by_isbn("9780517597675")?;
+        assert_eq!(book.rating, Some(4));
+        assert_eq!(book.shelf, Some("read".to_string()));
+        assert_eq!(book.date_read, Some("2021-03-14".to_string()));
+        assert_eq!(book.authors.len(), 4);
+
+        let report = import_models(read_library_export(&export_path())?, &book_repo, false)?;
+        assert!(report.added.is_empty());
+        assert_eq!(report.skipped.len(), 4);
+        Ok(())
+    }
+}
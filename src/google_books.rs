@@ -0,0 +1,275 @@
+use crate::error::{Error, Result};
+use crate::metadata::{surname_first, BookRecord, MetadataProvider, SearchQuery};
+use async_trait::async_trait;
+use serde_derive::Deserialize;
+
+const SEARCH_LIMIT: u32 = 20;
+
+#[derive(Debug, Deserialize)]
+struct VolumesResponse {
+    #[serde(default)]
+    items: Vec<Volume>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Volume {
+    volume_info: VolumeInfo,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VolumeInfo {
+    title: String,
+    subtitle: Option<String>,
+    #[serde(default)]
+    authors: Vec<String>,
+    publisher: Option<String>,
+    published_date: Option<String>,
+    description: Option<String>,
+    #[serde(default)]
+    industry_identifiers: Vec<IndustryIdentifier>,
+    page_count: Option<u32>,
+    #[serde(default)]
+    categories: Vec<String>,
+    image_links: Option<ImageLinks>,
+    language: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IndustryIdentifier {
+    #[serde(rename = "type")]
+    kind: String,
+    identifier: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ImageLinks {
+    small_thumbnail: Option<String>,
+    thumbnail: Option<String>,
+}
+
+impl VolumeInfo {
+    fn identifier(&self, kind: &str) -> Option<String> {
+        self.industry_identifiers
+            .iter()
+            .find(|id| id.kind == kind)
+            .map(|id| id.identifier.clone())
+    }
+}
+
+impl From<VolumeInfo> for BookRecord {
+    fn from(item: VolumeInfo) -> Self {
+        let isbn13 = item.identifier("ISBN_13");
+        let isbn10 = item.identifier("ISBN_10");
+        let title_long = item
+            .subtitle
+            .as_ref()
+            .map(|subtitle| format!("{}: {}", item.title, subtitle));
+        // The image links are given with plain HTTP, but the same URLs are served over HTTPS.
+        let image_url = item
+            .image_links
+            .and_then(|links| links.thumbnail.or(links.small_thumbnail))
+            .map(|url| url.replacen("http://", "https://", 1));
+        BookRecord {
+            title: item.title,
+            title_long,
+            authors: item.authors.iter().map(|a| surname_first(a)).collect(),
+            publisher: item.publisher,
+            edition: None,
+            date_published: item.published_date,
+            binding: None,
+            pages: item.page_count,
+            isbn13,
+            isbn10,
+            language: item.language,
+            subjects: item.categories,
+            synopsis: item.description,
+            image_url,
+            dimensions: None,
+            msrp: None,
+        }
+    }
+}
+
+/// Looks up books using the Google Books volumes API.
+///
+/// A key is optional, but requests without one are subject to a low shared quota.
+pub struct GoogleBooksRepository {
+    pub base_url: String,
+    pub api_key: Option<String>,
+}
+
+impl GoogleBooksRepository {
+    pub fn new(base_url: &str, api_key: Option<String>) -> GoogleBooksRepository {
+        GoogleBooksRepository {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+        }
+    }
+
+    async fn get_volumes(&self, query: &str) -> Result<Vec<Volume>> {
+        let url = format!("{}/volumes", self.base_url);
+        let mut params = vec![
+            ("q", query.to_string()),
+            ("maxResults", SEARCH_LIMIT.to_string()),
+        ];
+        if let Some(key) = &self.api_key {
+            params.push(("key", key.clone()));
+        }
+        let resp = reqwest::Client::new()
+            .get(&url)
+            .query(&params)
+            .header("accept", "application/json")
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        let response: VolumesResponse = serde_json::from_str(&resp)?;
+        Ok(response.items)
+    }
+}
+
+#[async_trait]
+impl MetadataProvider for GoogleBooksRepository {
+    async fn lookup_by_isbn(&self, isbn: &str) -> Result<BookRecord> {
+        let volume = self
+            .get_volumes(&format!("isbn:{isbn}"))
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::RecordNotFound {
+                provider: "Google Books".to_string(),
+                isbn: isbn.to_string(),
+            })?;
+        Ok(BookRecord::from(volume.volume_info))
+    }
+
+    async fn search(&self, query: &SearchQuery) -> Result<Vec<BookRecord>> {
+        let mut terms = Vec::new();
+        if let Some(title) = &query.title {
+            terms.push(format!("intitle:{title}"));
+        }
+        if let Some(author) = &query.author {
+            terms.push(format!("inauthor:{author}"));
+        }
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+        let volumes = self.get_volumes(&terms.join(" ")).await?;
+        Ok(volumes
+            .into_iter()
+            .map(|volume| BookRecord::from(volume.volume_info))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::GoogleBooksRepository;
+    use crate::error::Error;
+    use crate::metadata::{MetadataProvider, SearchQuery};
+    use color_eyre::Result;
+    use httpmock::prelude::*;
+
+    #[tokio::test]
+    async fn lookup_by_isbn_should_return_book_record() -> Result<()> {
+        let isbn = "9780517597675";
+        let server = MockServer::start();
+        let response_body = std::fs::read_to_string(
+            std::path::Path::new("resources").join("google_books_volumes_response_body.json"),
+        )?;
+        let volumes_mock = server.mock(|when, then| {
+            when.method(GET)
+                .path("/volumes")
+                .query_param("q", format!("isbn:{isbn}"))
+                .query_param("key", "api_key");
+            then.status(200).body(response_body);
+        });
+
+        let repository =
+            GoogleBooksRepository::new(&server.base_url(), Some("api_key".to_string()));
+        let record = repository.lookup_by_isbn(isbn).await?;
+
+        assert_eq!(record.title, "Two Seconds Under the World");
+        assert_eq!(
+            record.title_long,
+            Some("Two Seconds Under the World: Terror Comes to America--the Conspiracy Behind the World Trade Center Bombing".to_string())
+        );
+        assert_eq!(
+            record.authors,
+            vec![
+                "Dwyer, Jim",
+                "Murphy, Deidre",
+                "Tyre, Peg",
+                "Kocieniewski, David"
+            ]
+        );
+        assert_eq!(record.publisher, Some("Crown".to_string()));
+        assert_eq!(record.date_published, Some("1994".to_string()));
+        assert_eq!(record.pages, Some(322));
+        assert_eq!(record.subjects, vec!["History"]);
+        assert_eq!(record.synopsis, Some("Text and accompanying photographs present the story of the bombing of the World Trade Center by Islamic fundamentalist terrorists.".to_string()));
+        assert_eq!(
+            record.image_url,
+            Some("https://books.google.com/books/content?id=p3DuAAAAMAAJ&printsec=frontcover&img=1&zoom=1&source=gbs_api".to_string())
+        );
+        assert_eq!(record.isbn13, Some(isbn.to_string()));
+        assert_eq!(record.isbn10, Some("0517597675".to_string()));
+        assert_eq!(record.language, Some("en".to_string()));
+        assert_eq!(record.binding, None);
+        assert_eq!(record.edition, None);
+        volumes_mock.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn lookup_by_isbn_when_there_are_no_volumes_should_return_not_found_error() -> Result<()>
+    {
+        let server = MockServer::start();
+        let response_body = std::fs::read_to_string(
+            std::path::Path::new("resources").join("google_books_no_volumes_response_body.json"),
+        )?;
+        server.mock(|when, then| {
+            when.method(GET).path("/volumes");
+            then.status(200).body(response_body);
+        });
+
+        let repository = GoogleBooksRepository::new(&server.base_url(), None);
+        let result = repository.lookup_by_isbn("9780000000002").await;
+
+        assert!(matches!(result, Err(Error::RecordNotFound { .. })));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_should_return_book_records() -> Result<()> {
+        let server = MockServer::start();
+        let response_body = std::fs::read_to_string(
+            std::path::Path::new("resources").join("google_books_search_response_body.json"),
+        )?;
+        let search_mock = server.mock(|when, then| {
+            when.method(GET)
+                .path("/volumes")
+                .query_param("q", "intitle:two seconds inauthor:dwyer");
+            then.status(200).body(response_body);
+        });
+
+        let repository = GoogleBooksRepository::new(&server.base_url(), None);
+        let records = repository
+            .search(&SearchQuery {
+                title: Some("two seconds".to_string()),
+                author: Some("dwyer".to_string()),
+            })
+            .await?;
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].isbn13, Some("9780517597675".to_string()));
+        assert_eq!(records[1].isbn13, None);
+        assert_eq!(records[1].date_published, Some("1995-03".to_string()));
+        search_mock.assert();
+        Ok(())
+    }
+}
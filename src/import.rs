@@ -0,0 +1,507 @@
+use crate::books::BookRepository;
+use crate::error::{Error, Result};
+use crate::isbn::Isbn;
+use crate::metadata::MetadataProvider;
+use crate::models::AddBookModel;
+use prettytable::{Cell, Row, Table};
+use std::path::Path;
+
+/// An ISBN read from an import file, along with where it came from so problems can be reported.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IsbnEntry {
+    pub line: u64,
+    pub value: String,
+}
+
+/// Read the ISBNs from a file.
+///
+/// Without a column, the file has one ISBN per line. With one, the file is CSV and the column is
+/// either a header name or a number counting from 1; a header row is expected only for a name.
+/// Blank lines and empty values are ignored.
+pub fn read_isbns(path: &Path, column: Option<&str>) -> Result<Vec<IsbnEntry>> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| Error::ImportError(format!("{}: {e}", path.display())))?;
+    let Some(column) = column else {
+        return Ok(contents
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| IsbnEntry {
+                line: i as u64 + 1,
+                value: line.trim().to_string(),
+            })
+            .collect());
+    };
+
+    let index: Option<usize> = column.parse().ok();
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(index.is_none())
+        .flexible(true)
+        .from_reader(contents.as_bytes());
+    let index = match index {
+        Some(0) => {
+            return Err(Error::ImportError(
+                "Column numbers count from 1".to_string(),
+            ))
+        }
+        Some(index) => index - 1,
+        None => reader
+            .headers()?
+            .iter()
+            .position(|header| header.trim().eq_ignore_ascii_case(column))
+            .ok_or_else(|| Error::ImportError(format!("There is no column named {column}")))?,
+    };
+
+    let mut entries = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        let value = record.get(index).unwrap_or("").trim();
+        if value.is_empty() {
+            continue;
+        }
+        entries.push(IsbnEntry {
+            line: record.position().map_or(0, |p| p.line()),
+            value: value.to_string(),
+        });
+    }
+    Ok(entries)
+}
+
+/// The outcome of an import, listing each entry with the reason it was skipped or failed.
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    /// Nothing was saved, and `added` lists the books that would have been
+    pub dry_run: bool,
+    pub added: Vec<String>,
+    /// The IDs of the books that were saved, in the same order as `added`
+    pub added_ids: Vec<u32>,
+    pub skipped: Vec<(String, String)>,
+    pub failed: Vec<(String, String)>,
+}
+
+impl ImportReport {
+    pub fn print(&self) {
+        if self.dry_run && !self.added.is_empty() {
+            let mut table = Table::new();
+            table.set_titles(Row::new(vec![Cell::new("Would Add")]));
+            for entry in self.added.iter() {
+                table.add_row(Row::new(vec![Cell::new(entry)]));
+            }
+            table.printstd();
+        }
+        if !self.skipped.is_empty() || !self.failed.is_empty() {
+            let mut table = Table::new();
+            table.set_titles(Row::new(vec![
+                Cell::new("Entry"),
+                Cell::new("Outcome"),
+                Cell::new("Reason"),
+            ]));
+            for (entry, reason) in self.skipped.iter() {
+                table.add_row(Row::new(vec![
+                    Cell::new(entry),
+                    Cell::new("Skipped"),
+                    Cell::new(reason),
+                ]));
+            }
+            for (entry, reason) in self.failed.iter() {
+                table.add_row(Row::new(vec![
+                    Cell::new(entry),
+                    Cell::new("Failed"),
+                    Cell::new(reason),
+                ]));
+            }
+            table.printstd();
+        }
+        if self.dry_run {
+            println!(
+                "Dry run: would add {}, skip {}, fail {}. Nothing was saved.",
+                self.added.len(),
+                self.skipped.len(),
+                self.failed.len()
+            );
+        } else {
+            println!(
+                "Added {}, skipped {}, failed {}.",
+                self.added.len(),
+                self.skipped.len(),
+                self.failed.len()
+            );
+        }
+    }
+}
+
+/// Add books read from an export file to the database.
+///
+/// Each model is paired with a description used to identify it in the report. Books whose ISBN,
+/// or title and edition, is already in the database or earlier in the file are skipped, so the
+/// import can be run again.
+/// With `dry_run`, the books are checked in the same way but nothing is saved.
+pub fn import_models(
+    models: Vec<(String, Result<AddBookModel>)>,
+    book_repo: &BookRepository,
+    dry_run: bool,
+) -> Result<ImportReport> {
+    let mut report = ImportReport {
+        dry_run,
+        ..Default::default()
+    };
+    let mut seen: Vec<String> = Vec::new();
+    let mut seen_titles: Vec<(String, String)> = Vec::new();
+    for (entry, model) in models {
+        let mut model = match model {
+            Ok(model) => model,
+            Err(e) => {
+                report.failed.push((entry, e.to_string()));
+                continue;
+            }
+        };
+        if let Some(isbn) = &model.isbn {
+            let isbn = match isbn.parse::<Isbn>() {
+                Ok(isbn) => isbn.to_isbn13(),
+                Err(e) => {
+                    report.failed.push((entry, e.to_string()));
+                    continue;
+                }
+            };
+            if seen.contains(&isbn) {
+                report
+                    .skipped
+                    .push((entry, "Listed more than once".to_string()));
+                continue;
+            }
+            seen.push(isbn.clone());
+            match book_repo.get_by_isbn(&isbn) {
+                Ok(book) => {
+                    report.skipped.push((
+                        entry,
+                        format!("Already in the database with ID {}", book.id),
+                    ));
+                    continue;
+                }
+                Err(Error::BookNotFound(_)) => {}
+                Err(e) => return Err(e),
+            }
+            model.isbn = Some(isbn);
+        }
+        // Books are also unique by title and edition, so another edition of a book that's already
+        // listed, or in the database, can't be added until it's given an edition.
+        let title = (model.title.clone(), model.edition.clone());
+        if seen_titles.contains(&title) {
+            report.skipped.push((
+                entry,
+                "Listed more than once with the same title and edition".to_string(),
+            ));
+            continue;
+        }
+        seen_titles.push(title);
+        if let Some(id) = book_repo.find_id_by_title(&model.title, &model.edition)? {
+            let reason = match model.isbn {
+                Some(_) => format!("The book with ID {id} has the same title and edition"),
+                // Without an ISBN, the title and edition are the best indication of a duplicate.
+                None => format!("Already in the database with ID {id}"),
+            };
+            report.skipped.push((entry, reason));
+            continue;
+        }
+        if dry_run {
+            report.added.push(entry);
+            continue;
+        }
+        match book_repo.add_book(model) {
+            Ok(book) => {
+                report.added.push(entry);
+                report.added_ids.push(book.id);
+            }
+            Err(e) => report.failed.push((entry, e.to_string())),
+        }
+    }
+    Ok(report)
+}
+
+/// Look up the books for a list of ISBNs and add them to the database.
+///
+/// Books that are already in the database are skipped, so an import that was interrupted, or that
+/// had failures, can be run again. Each book is passed to `edit` before it's saved, which can
+/// change it or return `None` to leave it out. An error from `edit`, such as edited details that
+/// can't be parsed, is reported as a failure for that book and the import carries on.
+pub async fn import_isbns<F>(
+    entries: &[IsbnEntry],
+    provider: &dyn MetadataProvider,
+    book_repo: &BookRepository,
+    mut edit: F,
+) -> Result<ImportReport>
+where
+    F: FnMut(AddBookModel) -> Result<Option<AddBookModel>>,
+{
+    let mut report = ImportReport::default();
+    let mut to_look_up: Vec<String> = Vec::new();
+    for entry in entries {
+        let isbn = match entry.value.parse::<Isbn>() {
+            Ok(isbn) => isbn.to_isbn13(),
+            Err(e) => {
+                report.failed.push((
+                    format!("Line {}: {}", entry.line, entry.value),
+                    e.to_string(),
+                ));
+                continue;
+            }
+        };
+        if to_look_up.contains(&isbn) {
+            report
+                .skipped
+                .push((isbn, "Listed more than once".to_string()));
+            continue;
+        }
+        match book_repo.get_by_isbn(&isbn) {
+            Ok(book) => report
+                .skipped
+                .push((isbn, format!("Already in the database with ID {}", book.id))),
+            Err(Error::BookNotFound(_)) => to_look_up.push(isbn),
+            Err(e) => return Err(e),
+        }
+    }
+    if to_look_up.is_empty() {
+        return Ok(report);
+    }
+
+    // A failure here, such as running out of quota, affects every book that's left. Rather than
+    // abandon the report, they're recorded as failed so the import can be run again later.
+    let records = match provider.lookup_by_isbns(&to_look_up).await {
+        Ok(records) => records,
+        Err(e) => {
+            for isbn in to_look_up {
+                report.failed.push((isbn, e.to_string()));
+            }
+            return Ok(report);
+        }
+    };
+    for (isbn, record) in records {
+        let Some(record) = record else {
+            report
+                .failed
+                .push((isbn, "No record was found".to_string()));
+            continue;
+        };
+        let mut model = AddBookModel::from(record);
+        // The provider may give the ISBN in a different form, or not at all.
+        model.isbn = Some(isbn.clone());
+        let model = match edit(model) {
+            Ok(Some(model)) => model,
+            Ok(None) => {
+                report
+                    .skipped
+                    .push((isbn, "Not saved from the editor".to_string()));
+                continue;
+            }
+            Err(e) => {
+                report.failed.push((isbn, e.to_string()));
+                continue;
+            }
+        };
+        if let Some(id) = book_repo.find_id_by_title(&model.title, &model.edition)? {
+            report.skipped.push((
+                isbn,
+                format!("The book with ID {id} has the same title and edition"),
+            ));
+            continue;
+        }
+        match book_repo.add_book(model) {
+            Ok(book) => {
+                report.added.push(isbn);
+                report.added_ids.push(book.id);
+            }
+            Err(e) => report.failed.push((isbn, e.to_string())),
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{import_isbns, read_isbns, IsbnEntry};
+    use crate::books::BookRepository;
+    use crate::metadata::fake::FakeProvider;
+    use crate::metadata::BookRecord;
+    use assert_fs::prelude::*;
+    use color_eyre::Result;
+
+    fn records() -> Vec<BookRecord> {
+        vec![
+            BookRecord {
+                title: "The New Jackals".to_string(),
+                authors: vec!["Reeve, Simon".to_string()],
+                publisher: Some("Carlton Publishing Group".to_string()),
+                edition: Some("2nd".to_string()),
+                date_published: Some("2001".to_string()),
+                isbn13: Some("9780233050485".to_string()),
+                ..Default::default()
+            },
+            BookRecord {
+                title: "Two Seconds Under the World".to_string(),
+                authors: vec!["Dwyer, Jim".to_string()],
+                publisher: Some("Crown".to_string()),
+                edition: Some("1st".to_string()),
+                date_published: Some("1994".to_string()),
+                isbn13: Some("9780517597675".to_string()),
+                ..Default::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn read_isbns_should_read_one_isbn_per_line() -> Result<()> {
+        let storage_dir = assert_fs::TempDir::new()?;
+        let file = storage_dir.child("isbns.txt");
+        file.write_str("9780233050485\n\n  0-517-59767-5  \n")?;
+
+        let entries = read_isbns(file.path(), None)?;
+
+        assert_eq!(
+            entries,
+            vec![
+                IsbnEntry {
+                    line: 1,
+                    value: "9780233050485".to_string()
+                },
+                IsbnEntry {
+                    line: 3,
+                    value: "0-517-59767-5".to_string()
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn read_isbns_should_read_a_csv_column_by_name_or_number() -> Result<()> {
+        let storage_dir = assert_fs::TempDir::new()?;
+        let file = storage_dir.child("books.csv");
+        file.write_str(
+            "Title,ISBN\n\"Jackals, The New\",9780233050485\nNo ISBN,\nTwo Seconds,9780517597675\n",
+        )?;
+
+        let entries = read_isbns(file.path(), Some("isbn"))?;
+        let values: Vec<&str> = entries.iter().map(|e| e.value.as_str()).collect();
+        assert_eq!(values, vec!["9780233050485", "9780517597675"]);
+        assert_eq!(entries[1].line, 4);
+
+        // Without a header name, the header row is read as data.
+        let entries = read_isbns(file.path(), Some("2"))?;
+        let values: Vec<&str> = entries.iter().map(|e| e.value.as_str()).collect();
+        assert_eq!(values, vec!["ISBN", "9780233050485", "9780517597675"]);
+
+        assert!(read_isbns(file.path(), Some("price")).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn import_isbns_should_add_new_books_and_report_the_rest() -> Result<()> {
+        let storage_dir = assert_fs::TempDir::new()?;
+        let book_repo = BookRepository::new(storage_dir.child("books.db").to_path_buf());
+        let provider = FakeProvider { records: records() };
+        let entries: Vec<IsbnEntry> = [
+            "0233050485",
+            "9780517597675",
+            "978-0-233-05048-5",
+            "9780000000002",
+            "not an isbn",
+        ]
+        .iter()
+        .enumerate()
+        .map(|(i, value)| IsbnEntry {
+            line: i as u64 + 1,
+            value: value.to_string(),
+        })
+        .collect();
+
+        let report = import_isbns(&entries, &provider, &book_repo, |m| Ok(Some(m))).await?;
+
+        assert_eq!(report.added, vec!["9780233050485", "9780517597675"]);
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(report.skipped[0].0, "9780233050485");
+        assert_eq!(report.failed.len(), 2);
+        assert_eq!(report.failed[0].0, "Line 5: not an isbn");
+        assert_eq!(report.failed[1].0, "9780000000002");
+        let book = book_repo.get_by_isbn("9780233050485")?;
+        assert_eq!(book.title, "The New Jackals");
+
+        // Running the import again adds nothing.
+        let report = import_isbns(&entries, &provider, &book_repo, |m| Ok(Some(m))).await?;
+        assert!(report.added.is_empty());
+        assert_eq!(report.skipped.len(), 3);
+        assert_eq!(report.failed.len(), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn import_isbns_should_leave_out_books_not_saved_from_the_editor() -> Result<()> {
+        let storage_dir = assert_fs::TempDir::new()?;
+        let book_repo = BookRepository::new(storage_dir.child("books.db").to_path_buf());
+        let provider = FakeProvider { records: records() };
+        let entries = vec![
+            IsbnEntry {
+                line: 1,
+                value: "9780233050485".to_string(),
+            },
+            IsbnEntry {
+                line: 2,
+                value: "9780517597675".to_string(),
+            },
+        ];
+
+        let report = import_isbns(&entries, &provider, &book_repo, |mut m| {
+            if m.title == "The New Jackals" {
+                return Ok(None);
+            }
+            m.binding = "Hardcover".to_string();
+            Ok(Some(m))
+        })
+        .await?;
+
+        assert_eq!(report.added, vec!["9780517597675"]);
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(book_repo.get_by_isbn("9780517597675")?.binding, "Hardcover");
+        assert!(book_repo.get_by_isbn("9780233050485").is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn import_isbns_should_report_a_failed_edit_and_carry_on() -> Result<()> {
+        let storage_dir = assert_fs::TempDir::new()?;
+        let book_repo = BookRepository::new(storage_dir.child("books.db").to_path_buf());
+        let provider = FakeProvider { records: records() };
+        let entries = vec![
+            IsbnEntry {
+                line: 1,
+                value: "9780233050485".to_string(),
+            },
+            IsbnEntry {
+                line: 2,
+                value: "9780517597675".to_string(),
+            },
+        ];
+
+        let report = import_isbns(&entries, &provider, &book_repo, |m| {
+            if m.title == "The New Jackals" {
+                return "Pages: many".parse().map(Some);
+            }
+            Ok(Some(m))
+        })
+        .await?;
+
+        assert_eq!(report.added, vec!["9780517597675"]);
+        assert_eq!(
+            report.added_ids,
+            vec![book_repo.get_by_isbn("9780517597675")?.id]
+        );
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, "9780233050485");
+        assert!(book_repo.get_by_isbn("9780233050485").is_err());
+
+        // The failed book is added when the import is run again.
+        let report = import_isbns(&entries, &provider, &book_repo, |m| Ok(Some(m))).await?;
+        assert_eq!(report.added, vec!["9780233050485"]);
+        assert_eq!(report.skipped.len(), 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,335 @@
+use crate::error::{Error, Result};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// The range message published by the International ISBN Agency, which defines how ISBNs are
+/// divided into their registration group, registrant and publication elements.
+///
+/// To update it, download the XML version from https://www.isbn-international.org/range_file_generation
+/// and replace the file.
+const RANGE_MESSAGE: &str = include_str!("../resources/RangeMessage.xml");
+
+/// The number of digits that are compared against the ranges in the range message.
+const RANGE_DIGITS: usize = 7;
+
+/// An ISBN that has been validated.
+///
+/// ISBN-10s are converted on parsing, so the ISBN is always held in its 13 digit form.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Isbn {
+    digits: [u8; 13],
+}
+
+impl Isbn {
+    /// The ISBN-13 without any hyphens. This is the canonical form used for storage.
+    pub fn to_isbn13(&self) -> String {
+        self.digits.iter().map(|d| char::from(b'0' + d)).collect()
+    }
+
+    /// The ISBN-10 equivalent, if there is one.
+    ///
+    /// Only ISBNs with the 978 prefix have an ISBN-10.
+    pub fn to_isbn10(&self) -> Option<String> {
+        if self.digits[..3] != [9, 7, 8] {
+            return None;
+        }
+        let body = &self.digits[3..12];
+        let mut isbn10: String = body.iter().map(|d| char::from(b'0' + d)).collect();
+        isbn10.push(isbn10_check_digit(body));
+        Some(isbn10)
+    }
+
+    /// The ISBN-13 with hyphens separating its elements, e.g. 978-0-517-59767-5.
+    ///
+    /// This fails if the ISBN falls in a range that the bundled range message doesn't define,
+    /// which is the case for ranges that have not yet been allocated.
+    pub fn hyphenated(&self) -> Result<String> {
+        let isbn13 = self.to_isbn13();
+        let (group, registrant, publication) = self.elements()?;
+        Ok(format!(
+            "{}-{}-{}-{}-{}",
+            &isbn13[..3],
+            group,
+            registrant,
+            publication,
+            &isbn13[12..]
+        ))
+    }
+
+    /// The ISBN-10 with hyphens separating its elements, e.g. 0-517-59767-5.
+    pub fn hyphenated_isbn10(&self) -> Result<Option<String>> {
+        let Some(isbn10) = self.to_isbn10() else {
+            return Ok(None);
+        };
+        let (group, registrant, publication) = self.elements()?;
+        Ok(Some(format!(
+            "{}-{}-{}-{}",
+            group,
+            registrant,
+            publication,
+            &isbn10[9..]
+        )))
+    }
+
+    /// Split the ISBN into its registration group, registrant and publication elements.
+    fn elements(&self) -> Result<(String, String, String)> {
+        let isbn13 = self.to_isbn13();
+        let ranges = ranges();
+        let prefix = &isbn13[..3];
+        let rest = &isbn13[3..12];
+
+        let group_length = find_length(ranges, prefix, rest).ok_or_else(|| {
+            Error::InvalidIsbn(format!("{isbn13} is not in a defined registration group"))
+        })?;
+        let (group, rest) = rest.split_at(group_length);
+
+        let registrant_length = find_length(ranges, &format!("{prefix}-{group}"), rest)
+            .ok_or_else(|| {
+                Error::InvalidIsbn(format!("{isbn13} is not in a defined registrant range"))
+            })?;
+        if registrant_length >= rest.len() {
+            return Err(Error::InvalidIsbn(format!(
+                "{isbn13} is not in a defined registrant range"
+            )));
+        }
+        let (registrant, publication) = rest.split_at(registrant_length);
+        Ok((
+            group.to_string(),
+            registrant.to_string(),
+            publication.to_string(),
+        ))
+    }
+}
+
+impl std::fmt::Display for Isbn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_isbn13())
+    }
+}
+
+impl FromStr for Isbn {
+    type Err = Error;
+
+    /// Parse an ISBN-10 or ISBN-13, which may contain hyphens or spaces, validating its check
+    /// digit.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let stripped: Vec<char> = s
+            .trim()
+            .chars()
+            .filter(|c| *c != '-' && *c != ' ')
+            .collect();
+        match stripped.len() {
+            10 => {
+                let mut body = [0u8; 9];
+                for (i, c) in stripped[..9].iter().enumerate() {
+                    body[i] = to_digit(s, *c)?;
+                }
+                let check = stripped[9].to_ascii_uppercase();
+                if check != isbn10_check_digit(&body) {
+                    return Err(Error::InvalidIsbn(format!(
+                        "{s} does not have a valid ISBN-10 check digit"
+                    )));
+                }
+                let mut digits = [0u8; 13];
+                digits[..3].copy_from_slice(&[9, 7, 8]);
+                digits[3..12].copy_from_slice(&body);
+                digits[12] = isbn13_check_digit(&digits[..12]);
+                Ok(Isbn { digits })
+            }
+            13 => {
+                let mut digits = [0u8; 13];
+                for (i, c) in stripped.iter().enumerate() {
+                    digits[i] = to_digit(s, *c)?;
+                }
+                if digits[..3] != [9, 7, 8] && digits[..3] != [9, 7, 9] {
+                    return Err(Error::InvalidIsbn(format!(
+                        "{s} does not start with 978 or 979"
+                    )));
+                }
+                if digits[12] != isbn13_check_digit(&digits[..12]) {
+                    return Err(Error::InvalidIsbn(format!(
+                        "{s} does not have a valid ISBN-13 check digit"
+                    )));
+                }
+                Ok(Isbn { digits })
+            }
+            _ => Err(Error::InvalidIsbn(format!(
+                "{s} should have 10 or 13 digits"
+            ))),
+        }
+    }
+}
+
+fn to_digit(isbn: &str, c: char) -> Result<u8> {
+    c.to_digit(10)
+        .map(|d| d as u8)
+        .ok_or_else(|| Error::InvalidIsbn(format!("{isbn} contains the invalid character '{c}'")))
+}
+
+fn isbn10_check_digit(body: &[u8]) -> char {
+    let sum: u32 = body
+        .iter()
+        .enumerate()
+        .map(|(i, d)| (10 - i as u32) * *d as u32)
+        .sum();
+    match (11 - sum % 11) % 11 {
+        10 => 'X',
+        check => char::from(b'0' + check as u8),
+    }
+}
+
+fn isbn13_check_digit(body: &[u8]) -> u8 {
+    let sum: u32 = body
+        .iter()
+        .enumerate()
+        .map(|(i, d)| if i % 2 == 0 { *d as u32 } else { *d as u32 * 3 })
+        .sum();
+    ((10 - sum % 10) % 10) as u8
+}
+
+struct Rule {
+    start: u32,
+    end: u32,
+    length: usize,
+}
+
+/// Find the length of the next element, using the rules for the given prefix.
+///
+/// The rules are expressed in terms of the 7 digits following the prefix, padded with zeros
+/// when fewer remain.
+fn find_length(ranges: &HashMap<String, Vec<Rule>>, prefix: &str, rest: &str) -> Option<usize> {
+    let rules = ranges.get(prefix)?;
+    let mut value: String = rest.chars().take(RANGE_DIGITS).collect();
+    while value.len() < RANGE_DIGITS {
+        value.push('0');
+    }
+    let value: u32 = value.parse().ok()?;
+    rules
+        .iter()
+        .find(|rule| rule.start <= value && value <= rule.end)
+        .map(|rule| rule.length)
+        .filter(|length| *length > 0)
+}
+
+/// The rules from the range message, keyed by prefix: "978" for the registration groups, and
+/// e.g. "978-0" for the registrants in a group.
+fn ranges() -> &'static HashMap<String, Vec<Rule>> {
+    static RANGES: OnceLock<HashMap<String, Vec<Rule>>> = OnceLock::new();
+    RANGES.get_or_init(|| parse_range_message(RANGE_MESSAGE))
+}
+
+fn parse_range_message(xml: &str) -> HashMap<String, Vec<Rule>> {
+    let mut ranges: HashMap<String, Vec<Rule>> = HashMap::new();
+    let mut prefix = String::new();
+    let mut range = None;
+    for line in xml.lines().map(|line| line.trim()) {
+        if let Some(value) = element_text(line, "Prefix") {
+            prefix = value.to_string();
+        } else if let Some(value) = element_text(line, "Range") {
+            range = value.split_once('-').and_then(|(start, end)| {
+                Some((start.parse::<u32>().ok()?, end.parse::<u32>().ok()?))
+            });
+        } else if let Some(value) = element_text(line, "Length") {
+            if let (Some((start, end)), Ok(length)) = (range.take(), value.parse()) {
+                ranges
+                    .entry(prefix.clone())
+                    .or_default()
+                    .push(Rule { start, end, length });
+            }
+        }
+    }
+    ranges
+}
+
+fn element_text<'a>(line: &'a str, name: &str) -> Option<&'a str> {
+    line.strip_prefix(&format!("<{name}>"))?
+        .strip_suffix(&format!("</{name}>"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::Isbn;
+    use crate::error::Error;
+
+    #[test]
+    fn parse_should_accept_isbn13_with_or_without_separators() {
+        let isbn: Isbn = "9780517597675".parse().unwrap();
+        assert_eq!(isbn.to_isbn13(), "9780517597675");
+        let isbn: Isbn = "978-0-517-59767-5".parse().unwrap();
+        assert_eq!(isbn.to_isbn13(), "9780517597675");
+        let isbn: Isbn = "978 0 517 59767 5".parse().unwrap();
+        assert_eq!(isbn.to_isbn13(), "9780517597675");
+    }
+
+    #[test]
+    fn parse_should_convert_isbn10_to_isbn13() {
+        let isbn: Isbn = "0-233-05048-5".parse().unwrap();
+        assert_eq!(isbn.to_isbn13(), "9780233050485");
+        // A check digit of 10 is written as X.
+        let isbn: Isbn = "080442957x".parse().unwrap();
+        assert_eq!(isbn.to_isbn13(), "9780804429573");
+        assert_eq!(isbn.to_isbn10(), Some("080442957X".to_string()));
+    }
+
+    #[test]
+    fn parse_should_reject_invalid_isbns() {
+        assert!(matches!(
+            "9780517597676".parse::<Isbn>(),
+            Err(Error::InvalidIsbn(_))
+        ));
+        assert!(matches!(
+            "0517597676".parse::<Isbn>(),
+            Err(Error::InvalidIsbn(_))
+        ));
+        assert!(matches!(
+            "9770517597675".parse::<Isbn>(),
+            Err(Error::InvalidIsbn(_))
+        ));
+        assert!(matches!(
+            "97805175976".parse::<Isbn>(),
+            Err(Error::InvalidIsbn(_))
+        ));
+        assert!(matches!(
+            "97805175976A5".parse::<Isbn>(),
+            Err(Error::InvalidIsbn(_))
+        ));
+    }
+
+    #[test]
+    fn to_isbn10_should_only_convert_978_isbns() {
+        let isbn: Isbn = "9780517597675".parse().unwrap();
+        assert_eq!(isbn.to_isbn10(), Some("0517597675".to_string()));
+        let isbn: Isbn = "9791090636071".parse().unwrap();
+        assert_eq!(isbn.to_isbn10(), None);
+    }
+
+    #[test]
+    fn hyphenated_should_split_isbn_using_the_range_message() {
+        let cases = [
+            ("9780517597675", "978-0-517-59767-5"),
+            ("9780233050485", "978-0-233-05048-5"),
+            ("9783161484100", "978-3-16-148410-0"),
+            ("9781861978769", "978-1-86197-876-9"),
+            ("9789992158104", "978-99921-58-10-4"),
+            ("9791090636071", "979-10-90636-07-1"),
+        ];
+        for (isbn, expected) in cases {
+            let isbn: Isbn = isbn.parse().unwrap();
+            assert_eq!(isbn.hyphenated().unwrap(), expected);
+        }
+
+        let isbn: Isbn = "0517597675".parse().unwrap();
+        assert_eq!(
+            isbn.hyphenated_isbn10().unwrap(),
+            Some("0-517-59767-5".to_string())
+        );
+    }
+
+    #[test]
+    fn hyphenated_should_fail_for_an_undefined_range() {
+        // The 978-6-6 group range is not allocated.
+        let isbn: Isbn = "9786600000008".parse().unwrap();
+        assert!(matches!(isbn.hyphenated(), Err(Error::InvalidIsbn(_))));
+    }
+}
@@ -1,114 +1,142 @@
-use color_eyre::Result;
-use prettytable::{Cell, Row, Table};
+use crate::cache::ResponseCache;
+use crate::error::{Error, Result};
+use crate::isbn::Isbn;
+use crate::metadata::{BookRecord, MetadataProvider, SearchQuery};
+use crate::rate_limit::{RetryPolicy, Throttle};
+use async_trait::async_trait;
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Client, RequestBuilder, Response, StatusCode, Url};
+use serde::de::DeserializeOwned;
+use serde::{de, Deserializer};
+use serde_derive::Deserialize;
 use serde_json::Value;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Duration;
 
-const WRAP_LENGTH: usize = 80;
+const PROVIDER_NAME: &str = "ISBNdb";
+const SEARCH_PAGE_SIZE: u32 = 20;
 
+/// The most ISBNs the bulk endpoint accepts in a request on ISBNdb's basic plan.
+pub const DEFAULT_BULK_LIMIT: usize = 100;
+
+/// A book as returned by ISBNdb.
+///
+/// ISBNdb records are often incomplete, so everything other than the title is optional.
+#[derive(Clone, Debug, Default, Deserialize)]
 pub struct IsbnDbBook {
-    pub publisher: String,
-    pub language: String,
-    pub image_url: String,
-    pub title_long: String,
-    pub edition: String,
-    pub dimensions: String,
-    pub pages: u32,
-    pub date_published: String,
+    pub publisher: Option<String>,
+    pub language: Option<String>,
+    #[serde(rename = "image")]
+    pub image_url: Option<String>,
+    pub title_long: Option<String>,
+    pub edition: Option<String>,
+    pub dimensions: Option<String>,
+    #[serde(default, deserialize_with = "number_or_string")]
+    pub pages: Option<u32>,
+    pub date_published: Option<String>,
+    #[serde(default)]
     pub authors: Vec<String>,
+    #[serde(default)]
     pub title: String,
-    pub isbn13: String,
-    pub msrp: f32,
-    pub binding: String,
-    pub isbn: String,
-    pub isbn10: String,
+    pub isbn13: Option<String>,
+    #[serde(default, deserialize_with = "number_or_string")]
+    pub msrp: Option<f64>,
+    pub binding: Option<String>,
+    #[allow(dead_code)]
+    pub isbn: Option<String>,
+    pub isbn10: Option<String>,
     pub subjects: Option<Vec<String>>,
     pub synopsis: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+struct BookResponse {
+    book: IsbnDbBook,
+}
+
+#[derive(Debug, Deserialize)]
+struct BooksResponse {
+    #[serde(default)]
+    books: Vec<IsbnDbBook>,
+}
+
+/// The books are kept as they were received so they can be cached individually.
+#[derive(Debug, Deserialize)]
+struct BulkResponse {
+    #[serde(default)]
+    data: Vec<Value>,
+}
+
 impl IsbnDbBook {
-    pub fn print(&self) {
-        let mut table = Table::new();
-        let wrapped_title = textwrap::wrap(&self.title, WRAP_LENGTH).join("\n");
-        table.add_row(Row::new(vec![
-            Cell::new("Title"),
-            Cell::new(&wrapped_title),
-        ]));
-        if self.title_long != self.title {
-            let wrapped_long_title = textwrap::wrap(&self.title_long, 72).join("\n");
-            table.add_row(Row::new(vec![
-                Cell::new("Title (Long)"),
-                Cell::new(&wrapped_long_title),
-            ]));
+    /// Determine whether this is the book with the given ISBN, which may be an ISBN-10 or
+    /// ISBN-13.
+    fn has_isbn(&self, isbn: &str) -> bool {
+        let wanted = isbn.parse::<Isbn>().ok();
+        [&self.isbn13, &self.isbn10, &self.isbn]
+            .into_iter()
+            .flatten()
+            .any(|candidate| match (&wanted, candidate.parse::<Isbn>()) {
+                (Some(wanted), Ok(candidate)) => *wanted == candidate,
+                _ => candidate == isbn,
+            })
+    }
+}
+
+/// ISBNdb is inconsistent about numeric fields, sometimes giving them as strings such as
+/// "24.00", and sometimes as an empty string when there is no value.
+fn number_or_string<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: de::Deserialize<'de> + FromStr,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum NumberOrString<T> {
+        Number(T),
+        String(String),
+    }
+    Ok(
+        match <Option<NumberOrString<T>> as de::Deserialize>::deserialize(deserializer)? {
+            Some(NumberOrString::Number(n)) => Some(n),
+            Some(NumberOrString::String(s)) => s.trim().parse().ok(),
+            None => None,
+        },
+    )
+}
+
+impl From<IsbnDbBook> for BookRecord {
+    fn from(item: IsbnDbBook) -> Self {
+        let non_empty = |s: Option<String>| s.filter(|s| !s.trim().is_empty());
+        BookRecord {
+            title: item.title,
+            title_long: non_empty(item.title_long),
+            authors: item.authors,
+            publisher: non_empty(item.publisher),
+            edition: non_empty(item.edition),
+            date_published: non_empty(item.date_published),
+            binding: non_empty(item.binding),
+            pages: item.pages,
+            isbn13: non_empty(item.isbn13),
+            isbn10: non_empty(item.isbn10),
+            language: non_empty(item.language),
+            subjects: item.subjects.unwrap_or_default(),
+            synopsis: non_empty(item.synopsis),
+            image_url: non_empty(item.image_url),
+            dimensions: non_empty(item.dimensions),
+            msrp: item.msrp,
         }
-        table.add_row(Row::new(vec![
-            Cell::new("Author(s)"),
-            Cell::new(&self.authors.join("; ")),
-        ]));
-        table.add_row(Row::new(vec![
-            Cell::new("Date Published"),
-            Cell::new(&self.date_published),
-        ]));
-        table.add_row(Row::new(vec![
-            Cell::new("Binding"),
-            Cell::new(&self.binding),
-        ]));
-        table.add_row(Row::new(vec![
-            Cell::new("Edition"),
-            Cell::new(&self.edition),
-        ]));
-        table.add_row(Row::new(vec![
-            Cell::new("Pages"),
-            Cell::new(&self.pages.to_string()),
-        ]));
-        table.add_row(Row::new(vec![
-            Cell::new("Publisher"),
-            Cell::new(&self.publisher),
-        ]));
-        table.add_row(Row::new(vec![
-            Cell::new("Language"),
-            Cell::new(&self.language),
-        ]));
-
-        let wrapped_subjects = textwrap::wrap(
-            &self
-                .subjects
-                .as_ref()
-                .map(|subjects| subjects.join(", "))
-                .unwrap_or_else(|| "N/A".to_string()),
-            WRAP_LENGTH,
-        )
-        .join("\n");
-        table.add_row(Row::new(vec![
-            Cell::new("Subjects"),
-            Cell::new(&wrapped_subjects),
-        ]));
-
-        let wrapped_synopsis = textwrap::wrap(
-            self.synopsis.as_ref().unwrap_or(&"N/A".to_string()),
-            WRAP_LENGTH,
-        )
-        .join("\n");
-        table.add_row(Row::new(vec![
-            Cell::new("Synopsis"),
-            Cell::new(&wrapped_synopsis),
-        ]));
-        table.add_row(Row::new(vec![
-            Cell::new("MSRP"),
-            Cell::new(&self.msrp.to_string()),
-        ]));
-        table.add_row(Row::new(vec![Cell::new("ISBN13"), Cell::new(&self.isbn13)]));
-        table.add_row(Row::new(vec![Cell::new("ISBN"), Cell::new(&self.isbn)]));
-        table.add_row(Row::new(vec![Cell::new("ISBN10"), Cell::new(&self.isbn10)]));
-        table.add_row(Row::new(vec![
-            Cell::new("Dimensions"),
-            Cell::new(&self.dimensions),
-        ]));
-        table.printstd();
     }
 }
 
 pub struct IsbnDbRepository {
     pub base_url: String,
     pub rest_key: String,
+    pub cache: Option<ResponseCache>,
+    client: Client,
+    throttle: Throttle,
+    retry_policy: RetryPolicy,
+    bulk_limit: usize,
 }
 
 impl IsbnDbRepository {
@@ -116,57 +144,264 @@ impl IsbnDbRepository {
         IsbnDbRepository {
             base_url: base_url.to_string(),
             rest_key: rest_key.to_string(),
+            cache: None,
+            client: Client::new(),
+            throttle: Throttle::unlimited(),
+            retry_policy: RetryPolicy::default(),
+            bulk_limit: DEFAULT_BULK_LIMIT,
         }
     }
 
+    /// Set the most ISBNs to send in each bulk request. Higher ISBNdb plans allow more than the
+    /// default.
+    pub fn with_bulk_limit(mut self, bulk_limit: usize) -> IsbnDbRepository {
+        self.bulk_limit = bulk_limit.max(1);
+        self
+    }
+
+    /// Send no more than the given number of requests each second. ISBNdb plans have a limit,
+    /// and requests over it are refused.
+    pub fn with_rate_limit(mut self, requests_per_second: f64) -> IsbnDbRepository {
+        self.throttle = Throttle::new(requests_per_second);
+        self
+    }
+
+    #[cfg(test)]
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> IsbnDbRepository {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Use the cache for lookups by ISBN, so a book is only requested from ISBNdb once.
+    pub fn with_cache(mut self, cache: ResponseCache) -> IsbnDbRepository {
+        self.cache = Some(cache);
+        self
+    }
+
     pub async fn get_book_by_isbn(&self, isbn: &str) -> Result<IsbnDbBook> {
+        if let Some(cache) = &self.cache {
+            if let Some(body) = cache.get(PROVIDER_NAME, isbn)? {
+                let response: BookResponse = parse_body(&body)?;
+                return Ok(response.book);
+            }
+        }
+
         let url = format!("{}/book/{}", self.base_url, isbn);
-        let resp = reqwest::Client::new()
-            .get(&url)
+        let resp = self.send(self.client.get(&url)).await?;
+        if resp.status() == StatusCode::NOT_FOUND {
+            return Err(Error::RecordNotFound {
+                provider: PROVIDER_NAME.to_string(),
+                isbn: isbn.to_string(),
+            });
+        }
+        let body = response_body(resp).await?;
+        let response: BookResponse = parse_body(&body)?;
+        if let Some(cache) = &self.cache {
+            cache.save(PROVIDER_NAME, isbn, &body)?;
+        }
+        Ok(response.book)
+    }
+
+    /// Look up many books, using the bulk endpoint to resolve up to the bulk limit of ISBNs with
+    /// each request.
+    ///
+    /// The results are in the same order as the ISBNs, with `None` for the books ISBNdb has no
+    /// record of. Books that are already cached aren't requested.
+    pub async fn get_books_by_isbns(
+        &self,
+        isbns: &[String],
+    ) -> Result<Vec<(String, Option<IsbnDbBook>)>> {
+        let mut found: HashMap<String, IsbnDbBook> = HashMap::new();
+        let mut to_request: Vec<String> = Vec::new();
+        for isbn in isbns {
+            let cached = match &self.cache {
+                Some(cache) => cache.get(PROVIDER_NAME, isbn)?,
+                None => None,
+            };
+            match cached {
+                Some(body) => {
+                    let response: BookResponse = parse_body(&body)?;
+                    found.insert(isbn.clone(), response.book);
+                }
+                None if !to_request.contains(isbn) => to_request.push(isbn.clone()),
+                None => {}
+            }
+        }
+
+        let url = format!("{}/books", self.base_url);
+        for chunk in to_request.chunks(self.bulk_limit) {
+            let request = self.client.post(&url).form(&[("isbns", chunk.join(","))]);
+            let resp = self.send(request).await?;
+            // As with searches, a request where none of the books are found is a 404.
+            if resp.status() == StatusCode::NOT_FOUND {
+                continue;
+            }
+            let response: BulkResponse = parse_body(&response_body(resp).await?)?;
+            for value in response.data {
+                let book: IsbnDbBook = serde_json::from_value(value.clone()).map_err(malformed)?;
+                // The books aren't necessarily in the order requested, nor given with the same
+                // form of ISBN.
+                let Some(isbn) = chunk.iter().find(|isbn| book.has_isbn(isbn)) else {
+                    continue;
+                };
+                if let Some(cache) = &self.cache {
+                    let body = serde_json::json!({ "book": value }).to_string();
+                    cache.save(PROVIDER_NAME, isbn, &body)?;
+                }
+                found.insert(isbn.clone(), book);
+            }
+        }
+
+        Ok(isbns
+            .iter()
+            .map(|isbn| (isbn.clone(), found.get(isbn).cloned()))
+            .collect())
+    }
+
+    /// Search for books where the given column, either `title` or `author`, matches the query.
+    pub async fn search_books(&self, query: &str, column: &str) -> Result<Vec<IsbnDbBook>> {
+        let mut url = Url::parse(&self.base_url)
+            .map_err(|e| Error::ParseError(format!("Invalid ISBNdb URL: {e}")))?;
+        url.path_segments_mut()
+            .map_err(|_| Error::ParseError("Invalid ISBNdb URL".to_string()))?
+            .pop_if_empty()
+            .push("books")
+            .push(query);
+        let request = self.client.get(url).query(&[
+            ("page", "1".to_string()),
+            ("pageSize", SEARCH_PAGE_SIZE.to_string()),
+            ("column", column.to_string()),
+        ]);
+        let resp = self.send(request).await?;
+        // ISBNdb responds to a search without any matches as if the resource doesn't exist.
+        if resp.status() == StatusCode::NOT_FOUND {
+            return Ok(Vec::new());
+        }
+        let response: BooksResponse = parse_body(&response_body(resp).await?)?;
+        Ok(response.books)
+    }
+
+    /// Send a request, keeping to the rate limit and retrying when ISBNdb is unavailable or
+    /// refuses the request for being over the limit.
+    async fn send(&self, request: RequestBuilder) -> Result<Response> {
+        let request = request
             .header("accept", "application/json")
-            .header("Authorization", self.rest_key.clone())
-            .send()
-            .await?
-            .text()
-            .await?;
-        let v: Value = serde_json::from_str(&resp)?;
-        Ok(IsbnDbBook {
-            publisher: v["book"]["publisher"].as_str().unwrap().to_string(),
-            language: v["book"]["language"].as_str().unwrap().to_string(),
-            image_url: v["book"]["image"].as_str().unwrap().to_string(),
-            title_long: v["book"]["title_long"].as_str().unwrap().to_string(),
-            edition: v["book"]["edition"].as_str().unwrap().to_string(),
-            dimensions: v["book"]["dimensions"].as_str().unwrap().to_string(),
-            pages: v["book"]["pages"].as_i64().unwrap() as u32,
-            date_published: v["book"]["date_published"].as_str().unwrap().to_string(),
-            authors: v["book"]["authors"]
-                .as_array()
-                .unwrap()
-                .iter()
-                .map(|x| x.as_str().unwrap().to_string())
-                .collect(),
-            title: v["book"]["title"].as_str().unwrap().to_string(),
-            isbn13: v["book"]["isbn13"].as_str().unwrap().to_string(),
-            msrp: v["book"]["msrp"].as_f64().unwrap() as f32,
-            binding: v["book"]["binding"].as_str().unwrap().to_string(),
-            isbn: v["book"]["isbn"].as_str().unwrap().to_string(),
-            isbn10: v["book"]["isbn10"].as_str().unwrap().to_string(),
-            synopsis: v["book"]["synopsis"].as_str().map(String::from),
-            subjects: v["book"]["subjects"].as_array().map(|arr| {
-                arr.iter()
-                    .filter_map(|x| x.as_str())
-                    .map(String::from)
-                    .collect()
-            }),
-        })
+            .header("Authorization", self.rest_key.clone());
+        let mut attempt = 1;
+        loop {
+            self.throttle.wait().await;
+            // Only requests with a streaming body can't be cloned, and these have no body.
+            let result = request
+                .try_clone()
+                .expect("request should be cloneable")
+                .send()
+                .await;
+            let can_retry = attempt < self.retry_policy.max_attempts;
+            let mut delay = self.retry_policy.delay(attempt);
+            match result {
+                Err(e) if can_retry && (e.is_connect() || e.is_timeout()) => {}
+                Err(e) => return Err(e.into()),
+                Ok(resp) if resp.status() == StatusCode::TOO_MANY_REQUESTS => {
+                    let retry_after = retry_after(&resp);
+                    let body = resp.text().await.unwrap_or_default();
+                    if is_daily_limit(&body) {
+                        return Err(Error::QuotaExceeded(PROVIDER_NAME.to_string()));
+                    }
+                    // A wait longer than the policy allows isn't worth sitting through silently.
+                    let too_long =
+                        retry_after.is_some_and(|wait| wait > self.retry_policy.max_delay);
+                    if !can_retry || too_long {
+                        return Err(Error::RateLimited(PROVIDER_NAME.to_string()));
+                    }
+                    if let Some(retry_after) = retry_after {
+                        delay = delay.max(retry_after);
+                    }
+                }
+                Ok(resp) if can_retry && resp.status().is_server_error() => {}
+                Ok(resp) => return Ok(resp),
+            }
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+/// The delay requested by a `Retry-After` header given in seconds.
+fn retry_after(resp: &Response) -> Option<Duration> {
+    let seconds = resp.headers().get(RETRY_AFTER)?.to_str().ok()?;
+    Some(Duration::from_secs(seconds.trim().parse().ok()?))
+}
+
+/// ISBNdb uses 429 both for going over the per-second limit and for using up the plan's daily
+/// allowance. Only the message tells them apart, and there's no point retrying the latter.
+fn is_daily_limit(body: &str) -> bool {
+    body.to_lowercase().contains("daily")
+}
+
+/// Check the status of a response from ISBNdb and get its body.
+///
+/// Not found is handled by the callers, because its meaning depends on the request.
+async fn response_body(resp: Response) -> Result<String> {
+    match resp.status() {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+            Err(Error::Unauthorized(PROVIDER_NAME.to_string()))
+        }
+        _ => Ok(resp.error_for_status()?.text().await?),
+    }
+}
+
+fn parse_body<T: DeserializeOwned>(body: &str) -> Result<T> {
+    serde_json::from_str(body).map_err(malformed)
+}
+
+fn malformed(e: serde_json::Error) -> Error {
+    Error::MalformedResponse {
+        provider: PROVIDER_NAME.to_string(),
+        reason: e.to_string(),
+    }
+}
+
+#[async_trait]
+impl MetadataProvider for IsbnDbRepository {
+    async fn lookup_by_isbn(&self, isbn: &str) -> Result<BookRecord> {
+        let book = self.get_book_by_isbn(isbn).await?;
+        Ok(BookRecord::from(book))
+    }
+
+    async fn lookup_by_isbns(&self, isbns: &[String]) -> Result<Vec<(String, Option<BookRecord>)>> {
+        let books = self.get_books_by_isbns(isbns).await?;
+        Ok(books
+            .into_iter()
+            .map(|(isbn, book)| (isbn, book.map(BookRecord::from)))
+            .collect())
+    }
+
+    /// ISBNdb searches a single column at a time. When the query has both a title and an author,
+    /// the search is on the title and the results are then filtered by author.
+    async fn search(&self, query: &SearchQuery) -> Result<Vec<BookRecord>> {
+        let books = match (&query.title, &query.author) {
+            (Some(title), _) => self.search_books(title, "title").await?,
+            (None, Some(author)) => self.search_books(author, "author").await?,
+            (None, None) => return Ok(Vec::new()),
+        };
+        Ok(books
+            .into_iter()
+            .map(BookRecord::from)
+            .filter(|record| query.matches(record))
+            .collect())
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::IsbnDbRepository;
+    use crate::cache::ResponseCache;
+    use crate::error::Error;
+    use crate::metadata::{MetadataProvider, SearchQuery};
+    use crate::rate_limit::RetryPolicy;
     use color_eyre::Result;
     use httpmock::prelude::*;
+    use std::time::Duration;
 
     #[tokio::test]
     async fn get_book_by_isbn_should_return_book_record() -> Result<()> {
@@ -185,29 +420,29 @@ mod test {
         let repository = IsbnDbRepository::new(&server.base_url(), "api_key");
         let book = repository.get_book_by_isbn(isbn).await?;
 
-        assert_eq!(book.publisher, "Carlton Publishing Group");
-        assert_eq!(book.language, "en");
+        assert_eq!(book.publisher.as_deref(), Some("Carlton Publishing Group"));
+        assert_eq!(book.language.as_deref(), Some("en"));
         assert_eq!(
-            book.image_url,
-            "https://images.isbndb.com/covers/04/85/9780233050485.jpg"
+            book.image_url.as_deref(),
+            Some("https://images.isbndb.com/covers/04/85/9780233050485.jpg")
         );
         assert_eq!(
-            book.title_long,
-            "The New Jackals: Osama Bin Laden and the Future of Terrorism"
+            book.title_long.as_deref(),
+            Some("The New Jackals: Osama Bin Laden and the Future of Terrorism")
         );
-        assert_eq!(book.edition, "2nd");
-        assert_eq!(book.dimensions, "Height: 7.71652 Inches, Length: 5.07873 Inches, Weight: 0.661386786 Pounds, Width: 0.7874 Inches");
-        assert_eq!(book.pages, 352);
-        assert_eq!(book.date_published, "2001");
+        assert_eq!(book.edition.as_deref(), Some("2nd"));
+        assert_eq!(book.dimensions.as_deref(), Some("Height: 7.71652 Inches, Length: 5.07873 Inches, Weight: 0.661386786 Pounds, Width: 0.7874 Inches"));
+        assert_eq!(book.pages, Some(352));
+        assert_eq!(book.date_published.as_deref(), Some("2001"));
         assert_eq!(
             book.title,
             "The New Jackals: Osama Bin Laden and the Future of Terrorism"
         );
-        assert_eq!(book.isbn13, isbn);
-        assert_eq!(book.msrp, 17.75);
-        assert_eq!(book.binding, "Paperback");
-        assert_eq!(book.isbn, "0233050485");
-        assert_eq!(book.isbn10, "0233050485");
+        assert_eq!(book.isbn13.as_deref(), Some(isbn));
+        assert_eq!(book.msrp, Some(17.75));
+        assert_eq!(book.binding.as_deref(), Some("Paperback"));
+        assert_eq!(book.isbn.as_deref(), Some("0233050485"));
+        assert_eq!(book.isbn10.as_deref(), Some("0233050485"));
         assert_eq!(book.authors.len(), 1);
         assert_eq!(book.authors[0], "Reeve, Simon");
         assert_eq!(book.synopsis, None);
@@ -235,33 +470,30 @@ mod test {
         let repository = IsbnDbRepository::new(&server.base_url(), "api_key");
         let book = repository.get_book_by_isbn(isbn).await?;
 
-        assert_eq!(book.publisher, "Crown");
+        assert_eq!(book.publisher.as_deref(), Some("Crown"));
         assert_eq!(book.synopsis, Some("Text And Accompanying Photographs Present The Story Of The Bombing Of The World Trade Center By Islamic Fundamentalist Terrorists. Jim Dwyer ... [et Al.]. Includes Bibliographical References And Index.".to_string()));
-        assert_eq!(book.language, "en");
+        assert_eq!(book.language.as_deref(), Some("en"));
         assert_eq!(
-            book.image_url,
-            "https://images.isbndb.com/covers/76/75/9780517597675.jpg"
+            book.image_url.as_deref(),
+            Some("https://images.isbndb.com/covers/76/75/9780517597675.jpg")
         );
+        assert_eq!(book.title_long.as_deref(), Some("Two Seconds Under the World:Terror Comes to America-The Conspiracy Behind the World Trade Center Bombing"));
+        assert_eq!(book.edition.as_deref(), Some("1st"));
         assert_eq!(
-            book.title_long,
-            "Two Seconds Under the World:Terror Comes to America-The Conspiracy Behind the World Trade Center Bombing"
+            book.dimensions.as_deref(),
+            Some("Height: 9.5 Inches, Length: 6.25 Inches, Weight: 1.4 Pounds, Width: 1 Inches")
         );
-        assert_eq!(book.edition, "1st");
-        assert_eq!(
-            book.dimensions,
-            "Height: 9.5 Inches, Length: 6.25 Inches, Weight: 1.4 Pounds, Width: 1 Inches"
-        );
-        assert_eq!(book.pages, 322);
-        assert_eq!(book.date_published, "1997");
+        assert_eq!(book.pages, Some(322));
+        assert_eq!(book.date_published.as_deref(), Some("1997"));
         assert_eq!(
             book.title,
             "Two Seconds Under the World:Terror Comes to America-The Conspiracy Behind the World Trade Center Bombing"
         );
-        assert_eq!(book.isbn13, isbn);
-        assert_eq!(book.msrp, 24_f32);
-        assert_eq!(book.binding, "Hardcover");
-        assert_eq!(book.isbn, "0517597675");
-        assert_eq!(book.isbn10, "0517597675");
+        assert_eq!(book.isbn13.as_deref(), Some(isbn));
+        assert_eq!(book.msrp, Some(24.0));
+        assert_eq!(book.binding.as_deref(), Some("Hardcover"));
+        assert_eq!(book.isbn.as_deref(), Some("0517597675"));
+        assert_eq!(book.isbn10.as_deref(), Some("0517597675"));
         assert_eq!(book.authors.len(), 4);
         assert_eq!(book.authors[0], "Dwyer, Jim");
         assert_eq!(book.authors[1], "Murphy, Deidre");
@@ -281,4 +513,323 @@ mod test {
         latest_release_mock.assert();
         Ok(())
     }
+
+    #[tokio::test]
+    async fn search_should_query_the_title_column_and_filter_by_author() -> Result<()> {
+        let server = MockServer::start();
+        let response_body = std::fs::read_to_string(
+            std::path::Path::new("resources").join("books_search_response_body.json"),
+        )?;
+        let search_mock = server.mock(|when, then| {
+            when.method(GET)
+                .path("/books/terror")
+                .query_param("column", "title")
+                .query_param("page", "1");
+            then.status(200)
+                .header("server", "isbndb.com")
+                .body(response_body);
+        });
+
+        let repository = IsbnDbRepository::new(&server.base_url(), "api_key");
+        let query = SearchQuery {
+            title: Some("terror".to_string()),
+            author: Some("dwyer".to_string()),
+        };
+        let records = repository.search(&query).await?;
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].isbn13, Some("9780517597675".to_string()));
+        assert_eq!(records[0].publisher, Some("Crown".to_string()));
+        search_mock.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn get_book_by_isbn_when_book_has_missing_fields_should_return_book_record() -> Result<()>
+    {
+        let isbn = "9780140449112";
+        let server = MockServer::start();
+        let response_body = std::fs::read_to_string(
+            std::path::Path::new("resources").join("book_with_missing_fields_response_body.json"),
+        )?;
+        server.mock(|when, then| {
+            when.method(GET).path(format!("/book/{isbn}"));
+            then.status(200).body(response_body);
+        });
+
+        let repository = IsbnDbRepository::new(&server.base_url(), "api_key");
+        let record = repository.lookup_by_isbn(isbn).await?;
+
+        assert_eq!(record.title, "The Odyssey");
+        assert_eq!(record.authors, vec!["Homer"]);
+        assert_eq!(record.edition, None);
+        assert_eq!(record.dimensions, None);
+        assert_eq!(record.binding, None);
+        assert_eq!(record.pages, None);
+        assert_eq!(record.msrp, Some(12.99));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn get_book_by_isbn_with_cache_should_only_request_the_book_once() -> Result<()> {
+        let isbn = "9780233050485";
+        let storage_dir = assert_fs::TempDir::new().unwrap();
+        let database_path = storage_dir.path().join("books.db");
+        let server = MockServer::start();
+        let response_body = std::fs::read_to_string(
+            std::path::Path::new("resources").join("book_response_body.json"),
+        )?;
+        let book_mock = server.mock(|when, then| {
+            when.method(GET).path(format!("/book/{isbn}"));
+            then.status(200).body(response_body);
+        });
+
+        let repository = IsbnDbRepository::new(&server.base_url(), "api_key")
+            .with_cache(ResponseCache::new(database_path.clone(), false));
+        repository.get_book_by_isbn(isbn).await?;
+        let book = repository.get_book_by_isbn(isbn).await?;
+        assert_eq!(book.edition.as_deref(), Some("2nd"));
+        book_mock.assert_hits(1);
+
+        let repository = IsbnDbRepository::new(&server.base_url(), "api_key")
+            .with_cache(ResponseCache::new(database_path, true));
+        repository.get_book_by_isbn(isbn).await?;
+        book_mock.assert_hits(2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn get_books_by_isbns_should_request_books_in_chunks_and_report_missing_books(
+    ) -> Result<()> {
+        let storage_dir = assert_fs::TempDir::new().unwrap();
+        let database_path = storage_dir.path().join("books.db");
+        let server = MockServer::start();
+        let response_body = std::fs::read_to_string(
+            std::path::Path::new("resources").join("books_bulk_response_body.json"),
+        )?;
+        let found_mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/books")
+                .body_contains("0233050485")
+                .body_contains("9780517597675");
+            then.status(200).body(response_body);
+        });
+        let not_found_mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/books")
+                .body_contains("9780000000002");
+            then.status(404)
+                .body(r#"{"errorType":"NotFound","errorMessage":"Not Found"}"#);
+        });
+
+        let repository = IsbnDbRepository::new(&server.base_url(), "api_key")
+            .with_bulk_limit(2)
+            .with_cache(ResponseCache::new(database_path, false));
+        let isbns = vec![
+            "9780517597675".to_string(),
+            "0233050485".to_string(),
+            "9780000000002".to_string(),
+        ];
+        let books = repository.get_books_by_isbns(&isbns).await?;
+
+        assert_eq!(books.len(), 3);
+        assert_eq!(books[0].0, "9780517597675");
+        assert_eq!(
+            books[0].1.as_ref().unwrap().publisher.as_deref(),
+            Some("Crown")
+        );
+        assert_eq!(books[1].0, "0233050485");
+        assert_eq!(
+            books[1].1.as_ref().unwrap().isbn13.as_deref(),
+            Some("9780233050485")
+        );
+        assert_eq!(books[2].0, "9780000000002");
+        assert!(books[2].1.is_none());
+        found_mock.assert_hits(1);
+        not_found_mock.assert_hits(1);
+
+        // The books that were found are cached, so only the missing one is requested again.
+        let records = repository.lookup_by_isbns(&isbns).await?;
+        assert_eq!(
+            records[1].1.as_ref().unwrap().edition,
+            Some("2nd".to_string())
+        );
+        found_mock.assert_hits(1);
+        not_found_mock.assert_hits(2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn get_book_by_isbn_when_book_is_not_found_should_return_not_found_error() -> Result<()> {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(GET).path("/book/9780000000002");
+            then.status(404)
+                .body(r#"{"errorType":"NotFound","errorMessage":"Not Found"}"#);
+        });
+
+        let repository = IsbnDbRepository::new(&server.base_url(), "api_key");
+        let result = repository.get_book_by_isbn("9780000000002").await;
+
+        assert!(matches!(result, Err(Error::RecordNotFound { .. })));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn get_book_by_isbn_when_key_is_rejected_should_return_unauthorized_error() -> Result<()>
+    {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(GET).path("/book/9780233050485");
+            then.status(401).body(r#"{"message":"Unauthorized"}"#);
+        });
+
+        let repository = IsbnDbRepository::new(&server.base_url(), "bad_key");
+        let result = repository.get_book_by_isbn("9780233050485").await;
+
+        assert!(matches!(result, Err(Error::Unauthorized(_))));
+        Ok(())
+    }
+
+    fn fast_retries() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(10),
+        }
+    }
+
+    #[tokio::test]
+    async fn get_book_by_isbn_when_daily_quota_is_used_up_should_return_quota_exceeded_error(
+    ) -> Result<()> {
+        let server = MockServer::start();
+        let book_mock = server.mock(|when, then| {
+            when.method(GET).path("/book/9780233050485");
+            then.status(429)
+                .body(r#"{"message":"Daily limit exceeded for your plan"}"#);
+        });
+
+        let repository =
+            IsbnDbRepository::new(&server.base_url(), "api_key").with_retry_policy(fast_retries());
+        let result = repository.get_book_by_isbn("9780233050485").await;
+
+        assert!(matches!(result, Err(Error::QuotaExceeded(_))));
+        // There's no point retrying until tomorrow.
+        book_mock.assert_hits(1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn get_book_by_isbn_when_rate_limit_persists_should_retry_then_return_rate_limited_error(
+    ) -> Result<()> {
+        let server = MockServer::start();
+        let book_mock = server.mock(|when, then| {
+            when.method(GET).path("/book/9780233050485");
+            then.status(429).body(r#"{"message":"Too Many Requests"}"#);
+        });
+
+        let repository =
+            IsbnDbRepository::new(&server.base_url(), "api_key").with_retry_policy(fast_retries());
+        let result = repository.get_book_by_isbn("9780233050485").await;
+
+        assert!(matches!(result, Err(Error::RateLimited(_))));
+        book_mock.assert_hits(3);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn get_book_by_isbn_when_retry_after_is_too_long_should_return_rate_limited_error(
+    ) -> Result<()> {
+        let server = MockServer::start();
+        let book_mock = server.mock(|when, then| {
+            when.method(GET).path("/book/9780233050485");
+            then.status(429)
+                .header("Retry-After", "86400")
+                .body(r#"{"message":"Too Many Requests"}"#);
+        });
+
+        let repository =
+            IsbnDbRepository::new(&server.base_url(), "api_key").with_retry_policy(fast_retries());
+        let started = std::time::Instant::now();
+        let result = repository.get_book_by_isbn("9780233050485").await;
+
+        assert!(matches!(result, Err(Error::RateLimited(_))));
+        book_mock.assert_hits(1);
+        assert!(started.elapsed() < Duration::from_secs(5));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn get_book_by_isbn_when_server_fails_should_retry_then_return_http_error() -> Result<()>
+    {
+        let server = MockServer::start();
+        let book_mock = server.mock(|when, then| {
+            when.method(GET).path("/book/9780233050485");
+            then.status(503);
+        });
+
+        let repository =
+            IsbnDbRepository::new(&server.base_url(), "api_key").with_retry_policy(fast_retries());
+        let result = repository.get_book_by_isbn("9780233050485").await;
+
+        assert!(matches!(result, Err(Error::HttpError(_))));
+        book_mock.assert_hits(3);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn get_book_by_isbn_with_rate_limit_should_space_out_requests() -> Result<()> {
+        let server = MockServer::start();
+        let response_body = std::fs::read_to_string(
+            std::path::Path::new("resources").join("book_response_body.json"),
+        )?;
+        server.mock(|when, then| {
+            when.method(GET).path("/book/9780233050485");
+            then.status(200).body(response_body);
+        });
+
+        let repository = IsbnDbRepository::new(&server.base_url(), "api_key").with_rate_limit(10.0);
+        let start = std::time::Instant::now();
+        for _ in 0..3 {
+            repository.get_book_by_isbn("9780233050485").await?;
+        }
+
+        assert!(start.elapsed() >= Duration::from_millis(200));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn get_book_by_isbn_when_response_is_malformed_should_return_malformed_response_error(
+    ) -> Result<()> {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(GET).path("/book/9780233050485");
+            then.status(200).body("<html>Service Unavailable</html>");
+        });
+
+        let repository = IsbnDbRepository::new(&server.base_url(), "api_key");
+        let result = repository.get_book_by_isbn("9780233050485").await;
+
+        assert!(matches!(result, Err(Error::MalformedResponse { .. })));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_when_there_are_no_matches_should_return_no_records() -> Result<()> {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(GET).path("/books/zzzzzz");
+            then.status(404)
+                .body(r#"{"errorType":"NotFound","errorMessage":"Not Found"}"#);
+        });
+
+        let repository = IsbnDbRepository::new(&server.base_url(), "api_key");
+        let query = SearchQuery {
+            title: Some("zzzzzz".to_string()),
+            author: None,
+        };
+
+        assert!(repository.search(&query).await?.is_empty());
+        Ok(())
+    }
 }
@@ -0,0 +1,353 @@
+use crate::error::{Error, Result};
+use crate::models::{parse_rating, AddBookModel};
+use serde_derive::Deserialize;
+use std::path::Path;
+
+/// LibraryThing's own collections, which are imported as ownership and shelves rather than as
+/// tags.
+const MAPPED_COLLECTIONS: [&str; 4] = [
+    "Your library",
+    "To read",
+    "Currently reading",
+    "Read but unowned",
+];
+
+/// A row of the tab-separated file produced by LibraryThing's "Export" page.
+///
+/// Only the columns that are imported are listed; the rest are ignored.
+#[derive(Debug, Deserialize)]
+pub struct LibraryThingRow {
+    #[serde(rename = "Title")]
+    pub title: String,
+    #[serde(rename = "Primary Author", default)]
+    pub primary_author: String,
+    #[serde(rename = "Secondary Author", default)]
+    pub secondary_author: String,
+    #[serde(rename = "Secondary Author Roles", default)]
+    pub secondary_author_roles: String,
+    #[serde(rename = "Publication", default)]
+    pub publication: String,
+    #[serde(rename = "Date", default)]
+    pub date: String,
+    #[serde(rename = "Rating", default)]
+    pub rating: String,
+    #[serde(rename = "Media", default)]
+    pub media: String,
+    #[serde(rename = "Page Count", default)]
+    pub page_count: String,
+    #[serde(rename = "Date Read", default)]
+    pub date_read: String,
+    #[serde(rename = "Tags", default)]
+    pub tags: String,
+    #[serde(rename = "Collections", default)]
+    pub collections: String,
+    #[serde(rename = "ISBN", default)]
+    pub isbn: String,
+    #[serde(rename = "ISBNs", default)]
+    pub isbns: String,
+}
+
+impl LibraryThingRow {
+    /// A description of the row for reports, using the ISBN when it has one.
+    pub fn describe(&self) -> String {
+        match self.preferred_isbn() {
+            Some(isbn) => format!("{} ({isbn})", self.title),
+            None => self.title.clone(),
+        }
+    }
+
+    /// The first ISBN-13 in the ISBNs column, falling back to the bracketed ISBN column, which
+    /// often holds an ISBN-10.
+    fn preferred_isbn(&self) -> Option<String> {
+        let isbns = self
+            .isbns
+            .split(',')
+            .map(str::trim)
+            .filter(|isbn| !isbn.is_empty());
+        isbns
+            .clone()
+            .find(|isbn| isbn.len() == 13)
+            .or_else(|| isbns.clone().next())
+            .map(str::to_string)
+            .or_else(|| non_empty(self.isbn.trim_matches(|c| c == '[' || c == ']')))
+    }
+
+    fn in_collection(&self, name: &str) -> bool {
+        self.collections
+            .split(',')
+            .any(|collection| collection.trim().eq_ignore_ascii_case(name))
+    }
+
+    /// The tags, followed by any collections that aren't mapped to ownership or a shelf, such as
+    /// "Favorites" or the user's own collections.
+    fn tags(&self) -> Vec<String> {
+        let collections = self
+            .collections
+            .split(',')
+            .map(str::trim)
+            .filter(|collection| {
+                !MAPPED_COLLECTIONS
+                    .iter()
+                    .any(|mapped| collection.eq_ignore_ascii_case(mapped))
+            });
+        let mut tags: Vec<String> = Vec::new();
+        for tag in self.tags.split(',').map(str::trim).chain(collections) {
+            if !tag.is_empty() && !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+                tags.push(tag.to_string());
+            }
+        }
+        tags
+    }
+
+    /// LibraryThing's reading collections, as the shelf names used by Goodreads.
+    fn shelf(&self) -> Option<String> {
+        if self.in_collection("Currently reading") {
+            Some("currently-reading".to_string())
+        } else if self.in_collection("To read") {
+            Some("to-read".to_string())
+        } else if self.in_collection("Read but unowned") || !self.date_read.trim().is_empty() {
+            Some("read".to_string())
+        } else {
+            None
+        }
+    }
+}
+
+impl TryFrom<LibraryThingRow> for AddBookModel {
+    type Error = Error;
+
+    fn try_from(row: LibraryThingRow) -> Result<Self, Self::Error> {
+        // LibraryThing already gives names as "Surname, Forename". The secondary authors are
+        // separated by "|", with a matching list of roles; translators, editors and the like
+        // aren't treated as authors.
+        let roles = row.secondary_author_roles.split('|').map(str::trim);
+        let secondary_authors = row
+            .secondary_author
+            .split('|')
+            .map(str::trim)
+            .zip(roles.chain(std::iter::repeat("")))
+            .filter(|(_, role)| role.is_empty() || role.eq_ignore_ascii_case("Author"))
+            .map(|(name, _)| name);
+        let authors = std::iter::once(row.primary_author.trim())
+            .chain(secondary_authors)
+            .filter(|name| !name.is_empty())
+            .collect::<Vec<&str>>()
+            .join("; ");
+        let publication = Publication::parse(&row.publication);
+        // Ratings can be given in half stars, which are rounded.
+        let rating = match row.rating.trim() {
+            "" | "0" => None,
+            rating => {
+                let stars: f64 = rating.parse().map_err(|_| {
+                    Error::ParseError(format!("The rating should be from 1 to 5, not {rating}"))
+                })?;
+                Some(parse_rating(&stars.round().to_string())?)
+            }
+        };
+        let pages = match row.page_count.trim() {
+            "" => publication.pages.unwrap_or(0),
+            pages => pages.parse().map_err(|_| {
+                Error::ParseError(format!("Could not parse the number of pages {pages}"))
+            })?,
+        };
+        Ok(AddBookModel {
+            authors,
+            publisher: publication.publisher,
+            isbn: row.preferred_isbn(),
+            shelf: row.shelf(),
+            owned: row.in_collection("Your library"),
+            title: row.title.trim().to_string(),
+            edition: publication.edition,
+            date_published: row.date.trim().to_string(),
+            original_date_published: None,
+            price: None,
+            binding: row.media.trim().to_string(),
+            pages,
+            rating,
+            date_read: non_empty(&row.date_read),
+            tags: row.tags(),
+            short_title: None,
+            synopsis: None,
+            language: None,
+            dimensions: None,
+            image_url: None,
+            msrp: None,
+            isbn10: None,
+        })
+    }
+}
+
+/// The parts of LibraryThing's publication summary, such as
+/// `Crown (1994), Edition: 1st, 322 pages`.
+#[derive(Debug, Default, PartialEq)]
+struct Publication {
+    publisher: String,
+    edition: String,
+    pages: Option<u32>,
+}
+
+impl Publication {
+    fn parse(value: &str) -> Publication {
+        let mut parts = value.split(',').map(str::trim);
+        let publisher = parts.next().unwrap_or_default();
+        let publisher = match publisher.split_once(" (") {
+            Some((publisher, _)) => publisher,
+            None => publisher,
+        };
+        let mut publication = Publication {
+            publisher: publisher.trim().to_string(),
+            ..Default::default()
+        };
+        for part in parts {
+            if let Some(edition) = part.strip_prefix("Edition:") {
+                publication.edition = edition.trim().to_string();
+            } else if let Some(pages) = part.strip_suffix(" pages") {
+                publication.pages = pages.trim().parse().ok();
+            }
+        }
+        publication
+    }
+}
+
+/// Read the books from a LibraryThing tab-separated export.
+///
+/// Older exports are written in UTF-16, so the encoding is detected from the byte order mark.
+/// Each book is paired with a description for the import report, and rows that can't be
+/// converted are returned as errors.
+pub fn read_export(path: &Path) -> Result<Vec<(String, Result<AddBookModel>)>> {
+    let bytes = std::fs::read(path)
+        .map_err(|e| Error::ImportError(format!("Could not read {}: {e}", path.display())))?;
+    let text = decode(&bytes)?;
+    // The fields aren't quoted, so quotation marks in titles are kept as they are.
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(b'\t')
+        .quoting(false)
+        .flexible(true)
+        .from_reader(text.as_bytes());
+    let mut models = Vec::new();
+    for (i, row) in reader.deserialize::<LibraryThingRow>().enumerate() {
+        let row = match row {
+            Ok(row) => row,
+            Err(e) => {
+                models.push((format!("Row {}", i + 1), Err(e.into())));
+                continue;
+            }
+        };
+        let description = row.describe();
+        models.push((description, AddBookModel::try_from(row)));
+    }
+    Ok(models)
+}
+
+fn decode(bytes: &[u8]) -> Result<String> {
+    if let Some(bytes) = bytes.strip_prefix(&[0xff, 0xfe]) {
+        let units: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .collect();
+        return String::from_utf16(&units)
+            .map_err(|_| Error::ImportError("The file is not valid UTF-16".to_string()));
+    }
+    let bytes = bytes.strip_prefix(&[0xef, 0xbb, 0xbf]).unwrap_or(bytes);
+    String::from_utf8(bytes.to_vec())
+        .map_err(|_| Error::ImportError("The file is not valid UTF-8".to_string()))
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    let value = value.trim();
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{read_export, Publication};
+    use crate::books::BookRepository;
+    use crate::import::import_models;
+    use assert_fs::prelude::*;
+    use color_eyre::Result;
+    use std::path::{Path, PathBuf};
+
+    fn export_path(name: &str) -> PathBuf {
+        Path::new("resources").join(name)
+    }
+
+    #[test]
+    fn read_export_should_map_the_librarything_columns() -> Result<()> {
+        for name in ["librarything_export.tsv", "librarything_export_utf16.tsv"] {
+            let models = read_export(&export_path(name))?;
+            assert_eq!(models.len(), 3, "{name}");
+
+            let model = models[0].1.as_ref().unwrap();
+            assert_eq!(
+                model.authors,
+                "Dwyer, Jim; Kocieniewski, David; Murphy, Deidre; Tyre, Peg"
+            );
+            assert_eq!(model.isbn, Some("9780517597675".to_string()));
+            assert_eq!(model.publisher, "Crown");
+            assert_eq!(model.edition, "1st");
+            assert_eq!(model.binding, "Hardcover");
+            assert_eq!(model.pages, 322);
+            assert_eq!(model.date_published, "1994");
+            assert_eq!(model.rating, Some(4));
+            assert_eq!(model.shelf, Some("read".to_string()));
+            assert_eq!(model.date_read, Some("2021-03-14".to_string()));
+            assert_eq!(model.tags, vec!["terrorism", "new york"]);
+            assert!(model.owned);
+
+            let model = models[1].1.as_ref().unwrap();
+            assert_eq!(model.authors, "Reeve, Simon");
+            assert_eq!(model.isbn, Some("9780233050485".to_string()));
+            assert_eq!(model.pages, 352);
+            assert_eq!(model.rating, None);
+            assert_eq!(model.shelf, Some("to-read".to_string()));
+            assert!(!model.owned);
+
+            let model = models[2].1.as_ref().unwrap();
+            assert_eq!(models[2].0, "The Odyssey");
+            assert_eq!(model.authors, "Homer");
+            assert_eq!(model.isbn, None);
+            assert_eq!(model.publisher, "Penguin Classics");
+            assert_eq!(model.rating, Some(5));
+            assert_eq!(model.tags, vec!["classics", "poetry", "Favorites"]);
+            assert!(model.owned);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn publication_should_be_split_into_its_parts() {
+        assert_eq!(
+            Publication::parse("Crown (1994), Edition: 1st, 322 pages"),
+            Publication {
+                publisher: "Crown".to_string(),
+                edition: "1st".to_string(),
+                pages: Some(322),
+            }
+        );
+        assert_eq!(Publication::parse(""), Publication::default());
+    }
+
+    #[test]
+    fn import_should_add_books_with_and_without_isbns() -> Result<()> {
+        let storage_dir = assert_fs::TempDir::new()?;
+        let book_repo = BookRepository::new(storage_dir.child("books.db").to_path_buf());
+
+        let models = read_export(&export_path("librarything_export.tsv"))?;
+        let report = import_models(models, &book_repo, false)?;
+        assert_eq!(report.added.len(), 3);
+        assert!(report.failed.is_empty());
+        let book = book_repo.get_by_isbn("9780517597675")?;
+        assert_eq!(book.authors.len(), 4);
+        assert_eq!(book.authors[0].surname, "Dwyer");
+
+        let models = read_export(&export_path("librarything_export.tsv"))?;
+        let report = import_models(models, &book_repo, false)?;
+        assert!(report.added.is_empty());
+        assert_eq!(report.skipped.len(), 3);
+        Ok(())
+    }
+}
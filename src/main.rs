@@ -1,18 +1,46 @@
 mod books;
+mod cache;
+mod cite;
+mod config;
+mod covers;
 mod db;
 mod error;
+mod export;
+mod goodreads;
+mod google_books;
+mod import;
+mod isbn;
 mod isbn_db;
+mod librarything;
+mod marc;
+mod metadata;
+mod migrations;
 mod models;
+mod open_library;
+mod rate_limit;
+mod site;
 
-use books::BookRepository;
+use books::{Book, BookFilter, BookKey, BookRepository, SortBy};
+use cache::ResponseCache;
+use cite::CiteFormat;
 use clap::{Parser, Subcommand};
 use color_eyre::{eyre::eyre, Help, Report, Result};
+use config::Config;
+use covers::CoverStore;
 use dialoguer::{theme::ColorfulTheme, Confirm, Editor};
+use export::ExportFormat;
+use google_books::GoogleBooksRepository;
+use import::ImportReport;
+use isbn::Isbn;
 use isbn_db::IsbnDbRepository;
+use metadata::{MetadataProvider, ProviderKind, SearchQuery};
 use models::AddBookModel;
-use std::path::PathBuf;
+use open_library::OpenLibraryRepository;
+use std::path::{Path, PathBuf};
 
 const ISBNDB_URL: &str = "https://api2.isbndb.com";
+const OPEN_LIBRARY_URL: &str = "https://openlibrary.org";
+const GOOGLE_BOOKS_URL: &str = "https://www.googleapis.com/books/v1";
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -20,6 +48,11 @@ struct Cli {
     /// Provide a custom directory for database storage
     #[arg(short, long, value_name = "DIR", global(true))]
     storage_path: Option<PathBuf>,
+    /// The source used to look up books
+    ///
+    /// If not given, the `provider` setting in config.toml is used, or ISBNdb if there isn't one.
+    #[arg(short, long, value_enum, global(true))]
+    provider: Option<ProviderKind>,
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -28,51 +61,299 @@ struct Cli {
 enum Commands {
     /// Create the database schema
     Init,
-    /// Get the ISBNdb record for a book
+    /// Get the record for a book from the metadata provider
     ///
-    /// This will print the record for the book on the ISBNdb without saving it to the local
-    /// database.
+    /// This will print the record for the book without saving it to the local database. Several
+    /// ISBNs can be given, which ISBNdb looks up together.
     Get {
-        /// The book's ISBN
-        #[clap(name = "isbn")]
-        isbn: String,
+        /// The book's ISBN-10 or ISBN-13, which may contain hyphens
+        #[clap(name = "isbn", required = true)]
+        isbns: Vec<Isbn>,
+        /// Look the book up again rather than using a cached response
+        #[arg(long)]
+        refresh: bool,
     },
     /// Add a book to the database
     Add {
-        /// The book's ISBN
-        #[clap(name = "isbn")]
-        isbn: String,
+        /// The book's ISBN-10 or ISBN-13, which may contain hyphens
+        ///
+        /// This is optional when the book is entered manually.
+        #[clap(name = "isbn", required_unless_present = "manual")]
+        isbn: Option<Isbn>,
+        /// Enter the details in the editor rather than looking the book up
+        ///
+        /// Use this for books that have no ISBN or are not known to the metadata provider.
+        #[arg(long)]
+        manual: bool,
+        /// Look the book up again rather than using a cached response
+        #[arg(long)]
+        refresh: bool,
+    },
+    /// Search the metadata provider for books by title or author
+    Find {
+        /// Words from the title of the book
+        #[arg(long, required_unless_present = "author")]
+        title: Option<String>,
+        /// The name of one of the authors
+        #[arg(long)]
+        author: Option<String>,
+    },
+    /// List the books in the database
+    List {
+        /// The field to sort the books by
+        #[arg(long, value_enum, default_value_t = SortBy::Title)]
+        sort: SortBy,
+        /// Only list books that are owned (true) or not owned (false)
+        #[arg(long)]
+        owned: Option<bool>,
+        /// Only list books with this binding, e.g., Hardcover or Paperback
+        #[arg(long)]
+        binding: Option<String>,
+        /// Only list books from this publisher
+        #[arg(long)]
+        publisher: Option<String>,
+        /// Only list books with this tag
+        #[arg(long)]
+        tag: Option<String>,
+    },
+    /// Show a book stored in the database
+    Show {
+        /// The ID or ISBN of the book
+        #[clap(name = "id|isbn")]
+        key: BookKey,
+    },
+    /// Edit a book stored in the database
+    ///
+    /// The book will be opened in an external editor and saved when the editor is closed.
+    Edit {
+        /// The ID or ISBN of the book
+        #[clap(name = "id|isbn")]
+        key: BookKey,
+    },
+    /// Remove a book from the database
+    Remove {
+        /// The ID or ISBN of the book
+        #[clap(name = "id|isbn")]
+        key: BookKey,
+        /// Also remove any authors and publishers that no longer have any books
+        #[arg(long)]
+        remove_orphans: bool,
+    },
+    /// List the tags, with the number of books for each
+    Tags,
+    /// Add tags to a book
+    Tag {
+        /// The ID or ISBN of the book
+        #[clap(name = "id|isbn")]
+        key: BookKey,
+        /// The tags to add
+        #[clap(required = true)]
+        tags: Vec<String>,
+    },
+    /// Remove tags from a book
+    Untag {
+        /// The ID or ISBN of the book
+        #[clap(name = "id|isbn")]
+        key: BookKey,
+        /// The tags to remove
+        #[clap(required = true)]
+        tags: Vec<String>,
+    },
+    /// Search the books in the database
+    ///
+    /// The search terms are matched against the words in the title, author names and publisher
+    /// name. Books matching all the terms are listed, with the best matches first.
+    Search {
+        /// The terms to search for
+        #[clap(name = "terms", required = true)]
+        terms: Vec<String>,
+    },
+    /// Add books to the database from a file
+    Import {
+        #[command(subcommand)]
+        source: ImportSource,
+    },
+    /// Export every book in the database for spreadsheets and scripts
+    ///
+    /// A JSON export can be read back with `import json`.
+    Export {
+        #[arg(long, value_enum)]
+        format: ExportFormat,
+        /// The file to write, rather than printing the export
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Write citations for books, for use in papers
+    ///
+    /// Each citation has a key made from the first author's surname, the year and the first word
+    /// of the title, such as dwyer1994two.
+    Cite {
+        /// The IDs or ISBNs of the books
+        #[clap(name = "id|isbn", required_unless_present = "all")]
+        keys: Vec<BookKey>,
+        /// Cite every book in the database
+        #[arg(long, conflicts_with = "id|isbn")]
+        all: bool,
+        #[arg(long, value_enum, default_value_t = CiteFormat::Bibtex)]
+        format: CiteFormat,
+        /// The file to write, rather than printing the citations
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Generate a static website for browsing the collection
+    ///
+    /// The site has a searchable index of every book, and a page for each book, author and
+    /// publisher. It doesn't need a server, so it can be opened straight from the directory.
+    Site {
+        /// The directory to write the site to
+        output_dir: PathBuf,
+    },
+    /// Download the cover images for books
+    ///
+    /// Covers are kept in the covers directory alongside the database, and are used by the exports
+    /// and the website.
+    Cover {
+        #[command(subcommand)]
+        command: CoverCommands,
+    },
+    /// Manage the cache of responses from the metadata providers
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommands,
+    },
+}
+
+#[derive(Subcommand)]
+enum ImportSource {
+    /// Look up and add the books for a list of ISBNs
+    ///
+    /// Books that are already in the database are skipped, so the import can safely be run again
+    /// after fixing any failures.
+    Isbns {
+        /// A file with one ISBN per line, or a CSV file when a column is given
+        file: PathBuf,
+        /// The CSV column holding the ISBNs, either its header or its number counting from 1
+        #[arg(long)]
+        column: Option<String>,
+        /// Open each book in the editor before it's saved
+        #[arg(long)]
+        edit: bool,
+        /// Look the books up again rather than using cached responses
+        #[arg(long)]
+        refresh: bool,
+    },
+    /// Add the books from a Goodreads library export, including ratings and shelves
+    ///
+    /// Books that are already in the database are skipped.
+    Goodreads {
+        /// The CSV file from Goodreads' Export Library
+        file: PathBuf,
+        /// List what would be imported without saving anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Add the books from a LibraryThing tab-separated export
+    ///
+    /// Books in the "Your library" collection are marked as owned. Books that are already in the
+    /// database are skipped.
+    Librarything {
+        /// The tab-delimited file from LibraryThing's Export page
+        file: PathBuf,
+        /// List what would be imported without saving anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Add the books from a JSON file written by `export --format json`
+    ///
+    /// Books that are already in the database are skipped.
+    Json {
+        /// The exported JSON file
+        file: PathBuf,
+        /// List what would be imported without saving anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum CoverCommands {
+    /// Download the covers from the books' image URLs, replacing any already downloaded
+    Fetch {
+        /// The IDs or ISBNs of the books
+        #[clap(name = "id|isbn", required_unless_present = "missing")]
+        keys: Vec<BookKey>,
+        /// Fetch the covers for every book that has an image URL but no downloaded cover
+        #[arg(long, conflicts_with = "id|isbn")]
+        missing: bool,
     },
 }
 
+#[derive(Subcommand)]
+enum CacheCommands {
+    /// Delete every cached response
+    Clear,
+    /// Show the number of cached responses for each provider
+    Stats,
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Report> {
     color_eyre::install()?;
     let cli = Cli::parse();
-    let database_path = get_database_path(cli.storage_path)?;
-    let isbn_db_key = match std::env::var("ISBNDB_KEY") {
-        Ok(val) => val,
-        Err(_) => {
-            return Err(eyre!("Could not obtain a key for the ISBNdb database")
-                .suggestion("Please set the ISBNDB_KEY variable to your key"));
-        }
-    };
+    let storage_path = get_storage_path(cli.storage_path)?;
+    let database_path = storage_path.join("books.db");
+    let config = Config::load(&storage_path.join(config::CONFIG_FILE_NAME))?;
+    let provider_kind = cli.provider.or(config.provider).unwrap_or_default();
 
     match cli.command {
         Some(Commands::Init) => {
             db::init_db(database_path)?;
             Ok(())
         }
-        Some(Commands::Get { isbn }) => {
-            let isbn_repo = IsbnDbRepository::new(ISBNDB_URL, &isbn_db_key);
-            let book = isbn_repo.get_book_by_isbn(&isbn).await?;
-            book.print();
+        Some(Commands::Get { isbns, refresh }) => {
+            let cache = ResponseCache::new(database_path, refresh);
+            let provider = create_provider(provider_kind, &config, cache)?;
+            if let [isbn] = isbns.as_slice() {
+                let record = provider.lookup_by_isbn(&isbn.to_isbn13()).await?;
+                record.print();
+                return Ok(());
+            }
+            let isbns: Vec<String> = isbns.iter().map(Isbn::to_isbn13).collect();
+            for (isbn, record) in provider.lookup_by_isbns(&isbns).await? {
+                match record {
+                    Some(record) => record.print(),
+                    None => println!("No record was found for ISBN {isbn}."),
+                }
+            }
             Ok(())
         }
-        Some(Commands::Add { isbn }) => {
-            let isbn_repo = IsbnDbRepository::new(ISBNDB_URL, &isbn_db_key);
-            let book = isbn_repo.get_book_by_isbn(&isbn).await?;
-            let mut model = AddBookModel::from(book);
+        Some(Commands::Add {
+            isbn,
+            manual,
+            refresh,
+        }) => {
+            if manual {
+                let to_edit = AddBookModel::blank(isbn.map(|isbn| isbn.to_isbn13())).to_editor();
+                let Some(edited) = Editor::new().edit(&to_edit)? else {
+                    println!("The book was not saved.");
+                    return Ok(());
+                };
+                let model: AddBookModel = edited.parse()?;
+                let book_repo = BookRepository::new(database_path);
+                let book = book_repo.add_book(model)?;
+                println!("Saved book to the database.");
+                download_cover(&storage_path, &book_repo, &book).await;
+                return Ok(());
+            }
+
+            // Clap ensures the ISBN is present when the book isn't being entered manually.
+            let Some(isbn) = isbn else {
+                return Ok(());
+            };
+            let cache = ResponseCache::new(database_path.clone(), refresh);
+            let provider = create_provider(provider_kind, &config, cache)?;
+            let record = provider.lookup_by_isbn(&isbn.to_isbn13()).await?;
+            let mut model = AddBookModel::from(record);
             println!("Retrieved book with ISBN {isbn}");
             model.print();
             if Confirm::with_theme(&ColorfulTheme::default())
@@ -87,8 +368,273 @@ async fn main() -> Result<(), Report> {
             }
 
             let book_repo = BookRepository::new(database_path);
-            book_repo.add_book(model)?;
+            let book = book_repo.add_book(model)?;
             println!("Saved book to the database.");
+            download_cover(&storage_path, &book_repo, &book).await;
+            Ok(())
+        }
+        Some(Commands::Find { title, author }) => {
+            // Searches aren't cached, only lookups by ISBN.
+            let cache = ResponseCache::new(database_path, false);
+            let provider = create_provider(provider_kind, &config, cache)?;
+            let records = provider.search(&SearchQuery { title, author }).await?;
+            if records.is_empty() {
+                println!("No books found.");
+                return Ok(());
+            }
+            metadata::print_records(&records);
+            Ok(())
+        }
+        Some(Commands::List {
+            sort,
+            owned,
+            binding,
+            publisher,
+            tag,
+        }) => {
+            let book_repo = BookRepository::new(database_path);
+            let filter = BookFilter {
+                owned,
+                binding,
+                publisher,
+                tag,
+            };
+            let books = book_repo.list(sort, &filter)?;
+            if books.is_empty() {
+                println!("No books found.");
+                return Ok(());
+            }
+            books::print_books(&books);
+            Ok(())
+        }
+        Some(Commands::Show { key }) => {
+            let book_repo = BookRepository::new(database_path);
+            let book = book_repo.get(&key)?;
+            book.print();
+            Ok(())
+        }
+        Some(Commands::Edit { key }) => {
+            let book_repo = BookRepository::new(database_path);
+            let book = book_repo.get(&key)?;
+            let id = book.id;
+            let model = AddBookModel::from(book);
+            let to_edit = model.to_editor();
+            match Editor::new().edit(&to_edit)? {
+                Some(edited) if edited != to_edit => {
+                    let model: AddBookModel = edited.parse()?;
+                    let book = book_repo.update_book(id, model)?;
+                    book.print();
+                    println!("Saved changes to the database.");
+                }
+                _ => println!("No changes were made."),
+            }
+            Ok(())
+        }
+        Some(Commands::Tags) => {
+            let book_repo = BookRepository::new(database_path);
+            let tags = book_repo.tags()?;
+            if tags.is_empty() {
+                println!("No tags found.");
+                return Ok(());
+            }
+            books::print_tags(&tags);
+            Ok(())
+        }
+        Some(Commands::Tag { key, tags }) => {
+            let book_repo = BookRepository::new(database_path);
+            let book = book_repo.tag_book(&key, tags)?;
+            println!("{} is tagged: {}", book.title, book.tags.join("; "));
+            Ok(())
+        }
+        Some(Commands::Untag { key, tags }) => {
+            let book_repo = BookRepository::new(database_path);
+            let book = book_repo.untag_book(&key, tags)?;
+            if book.tags.is_empty() {
+                println!("{} has no tags.", book.title);
+            } else {
+                println!("{} is tagged: {}", book.title, book.tags.join("; "));
+            }
+            Ok(())
+        }
+        Some(Commands::Remove {
+            key,
+            remove_orphans,
+        }) => {
+            let book_repo = BookRepository::new(database_path);
+            let book = book_repo.get(&key)?;
+            book.print();
+            if Confirm::with_theme(&ColorfulTheme::default())
+                .with_prompt("Remove this book from the database?")
+                .default(false)
+                .interact()?
+            {
+                book_repo.remove_book(book.id, remove_orphans)?;
+                println!("Removed book from the database.");
+            }
+            Ok(())
+        }
+        Some(Commands::Search { terms }) => {
+            let book_repo = BookRepository::new(database_path);
+            let results = book_repo.search(&terms)?;
+            if results.is_empty() {
+                println!("No books found.");
+                return Ok(());
+            }
+            for result in results.iter() {
+                result.print();
+            }
+            Ok(())
+        }
+        Some(Commands::Import {
+            source:
+                ImportSource::Isbns {
+                    file,
+                    column,
+                    edit,
+                    refresh,
+                },
+        }) => {
+            let entries = import::read_isbns(&file, column.as_deref())?;
+            let cache = ResponseCache::new(database_path.clone(), refresh);
+            let provider = create_provider(provider_kind, &config, cache)?;
+            let book_repo = BookRepository::new(database_path);
+            let report = import::import_isbns(&entries, provider.as_ref(), &book_repo, |model| {
+                if !edit {
+                    return Ok(Some(model));
+                }
+                println!("Editing {}", model.title);
+                match Editor::new().edit(&model.to_editor()) {
+                    Ok(Some(edited)) => edited.parse().map(Some),
+                    Ok(None) => Ok(None),
+                    Err(e) => Err(error::Error::ImportError(e.to_string())),
+                }
+            })
+            .await?;
+            report.print();
+            download_import_covers(&storage_path, &book_repo, &report).await?;
+            Ok(())
+        }
+        Some(Commands::Import {
+            source: ImportSource::Goodreads { file, dry_run },
+        }) => {
+            let models = goodreads::read_library_export(&file)?;
+            let book_repo = BookRepository::new(database_path);
+            let report = import::import_models(models, &book_repo, dry_run)?;
+            report.print();
+            download_import_covers(&storage_path, &book_repo, &report).await?;
+            Ok(())
+        }
+        Some(Commands::Import {
+            source: ImportSource::Librarything { file, dry_run },
+        }) => {
+            let models = librarything::read_export(&file)?;
+            let book_repo = BookRepository::new(database_path);
+            let report = import::import_models(models, &book_repo, dry_run)?;
+            report.print();
+            download_import_covers(&storage_path, &book_repo, &report).await?;
+            Ok(())
+        }
+        Some(Commands::Import {
+            source: ImportSource::Json { file, dry_run },
+        }) => {
+            let models = export::read_json(&file)?;
+            let book_repo = BookRepository::new(database_path);
+            let report = import::import_models(models, &book_repo, dry_run)?;
+            report.print();
+            download_import_covers(&storage_path, &book_repo, &report).await?;
+            Ok(())
+        }
+        Some(Commands::Export { format, output }) => {
+            let book_repo = BookRepository::new(database_path);
+            let books = export::books_to_export(&book_repo)?;
+            match output {
+                Some(path) => {
+                    let file = std::fs::File::create(&path)?;
+                    export::write_books(&books, format, std::io::BufWriter::new(file))?;
+                }
+                None => export::write_books(&books, format, std::io::stdout().lock())?,
+            }
+            Ok(())
+        }
+        Some(Commands::Cite {
+            keys,
+            all,
+            format,
+            output,
+        }) => {
+            let book_repo = BookRepository::new(database_path);
+            // Every book is needed to keep the citation keys the same however books are cited.
+            let library = export::books_to_export(&book_repo)?;
+            let selected = keys
+                .iter()
+                .map(|key| book_repo.get(key))
+                .collect::<Result<Vec<_>, _>>()?;
+            let books = if all { &library } else { &selected };
+            match output {
+                Some(path) => {
+                    let file = std::fs::File::create(&path)?;
+                    cite::write_citations(books, &library, format, std::io::BufWriter::new(file))?;
+                }
+                None => cite::write_citations(books, &library, format, std::io::stdout().lock())?,
+            }
+            Ok(())
+        }
+        Some(Commands::Site { output_dir }) => {
+            let book_repo = BookRepository::new(database_path);
+            let books = export::books_to_export(&book_repo)?;
+            let pages = site::generate(&books, &output_dir)?;
+            println!("Wrote {pages} pages to {}", output_dir.display());
+            Ok(())
+        }
+        Some(Commands::Cover {
+            command: CoverCommands::Fetch { keys, missing },
+        }) => {
+            let book_repo = BookRepository::new(database_path);
+            let books = if missing {
+                export::books_to_export(&book_repo)?
+                    .into_iter()
+                    .filter(covers::is_missing)
+                    .collect()
+            } else {
+                keys.iter()
+                    .map(|key| book_repo.get(key))
+                    .collect::<Result<Vec<_>, _>>()?
+            };
+            let store = CoverStore::new(&storage_path);
+            let results = covers::fetch_covers(&books, &store, &book_repo).await;
+            if results.is_empty() {
+                println!("There are no covers to fetch.");
+                return Ok(());
+            }
+            let mut fetched = 0;
+            for (title, result) in results.iter() {
+                match result {
+                    Ok(_) => {
+                        fetched += 1;
+                        println!("Downloaded the cover for {title}");
+                    }
+                    Err(e) => println!("Could not download the cover for {title}: {e}"),
+                }
+            }
+            println!("Downloaded {fetched} of {} covers.", results.len());
+            Ok(())
+        }
+        Some(Commands::Cache { command }) => {
+            let cache = ResponseCache::new(database_path, false);
+            match command {
+                CacheCommands::Clear => {
+                    let cleared = cache.clear()?;
+                    println!("Cleared {cleared} cached responses.");
+                }
+                CacheCommands::Stats => {
+                    let stats = cache.stats()?;
+                    if stats.is_empty() {
+                        println!("The cache is empty.");
+                    } else {
+                        cache::print_cache_stats(&stats);
+                    }
+                }
+            }
             Ok(())
         }
         None => {
@@ -98,11 +644,88 @@ async fn main() -> Result<(), Report> {
     }
 }
 
-fn get_database_path(storage_path: Option<PathBuf>) -> Result<PathBuf> {
+fn create_provider(
+    kind: ProviderKind,
+    config: &Config,
+    cache: ResponseCache,
+) -> Result<Box<dyn MetadataProvider>> {
+    match kind {
+        ProviderKind::IsbnDb => {
+            let isbn_db_key = get_isbn_db_key()?;
+            Ok(Box::new(
+                IsbnDbRepository::new(ISBNDB_URL, &isbn_db_key)
+                    .with_cache(cache)
+                    .with_rate_limit(config.isbndb.requests_per_second)
+                    .with_bulk_limit(config.isbndb.bulk_limit),
+            ))
+        }
+        ProviderKind::OpenLibrary => Ok(Box::new(OpenLibraryRepository::new(OPEN_LIBRARY_URL))),
+        ProviderKind::GoogleBooks => {
+            let google_books_key = std::env::var("GOOGLE_BOOKS_KEY").ok();
+            Ok(Box::new(GoogleBooksRepository::new(
+                GOOGLE_BOOKS_URL,
+                google_books_key,
+            )))
+        }
+    }
+}
+
+/// Download the cover for a book that has just been added.
+///
+/// A failed download doesn't undo the add, as the cover can be fetched later with
+/// `cover fetch --missing`.
+async fn download_cover(storage_path: &Path, book_repo: &BookRepository, book: &Book) {
+    let store = CoverStore::new(storage_path);
+    for (_, result) in covers::fetch_covers(std::slice::from_ref(book), &store, book_repo).await {
+        match result {
+            Ok(cover) => println!("Saved the cover to {}", cover.path.display()),
+            Err(e) => println!("Could not download the cover: {e}"),
+        }
+    }
+}
+
+/// Download the covers for the books added by an import. Failures are listed, but don't affect
+/// the books, which have already been saved.
+async fn download_import_covers(
+    storage_path: &Path,
+    book_repo: &BookRepository,
+    report: &ImportReport,
+) -> Result<()> {
+    let books: Vec<Book> = report
+        .added_ids
+        .iter()
+        .map(|id| book_repo.get_by_id(*id))
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .filter(covers::is_missing)
+        .collect();
+    if books.is_empty() {
+        return Ok(());
+    }
+    let store = CoverStore::new(storage_path);
+    let results = covers::fetch_covers(&books, &store, book_repo).await;
+    for (title, result) in results.iter() {
+        if let Err(e) = result {
+            println!("Could not download the cover for {title}: {e}");
+        }
+    }
+    let fetched = results.iter().filter(|(_, result)| result.is_ok()).count();
+    println!("Downloaded {fetched} of {} covers.", results.len());
+    Ok(())
+}
+
+fn get_isbn_db_key() -> Result<String> {
+    match std::env::var("ISBNDB_KEY") {
+        Ok(val) => Ok(val),
+        Err(_) => Err(eyre!("Could not obtain a key for the ISBNdb database")
+            .suggestion("Please set the ISBNDB_KEY variable to your key")),
+    }
+}
+
+fn get_storage_path(storage_path: Option<PathBuf>) -> Result<PathBuf> {
     let data_dir_path =
         dirs_next::data_dir().ok_or_else(|| eyre!("Unable to retrieve data directory"))?;
     let storage_path = storage_path.unwrap_or(data_dir_path.join("books-db"));
     std::fs::create_dir_all(storage_path.clone())?;
-    let database_path = storage_path.join("books.db");
-    Ok(database_path)
+    Ok(storage_path)
 }
@@ -0,0 +1,481 @@
+use crate::books::Book;
+use crate::error::Result;
+use std::io::Write;
+
+const SUBFIELD_DELIMITER: u8 = 0x1f;
+const FIELD_TERMINATOR: u8 = 0x1e;
+const RECORD_TERMINATOR: u8 = 0x1d;
+const LEADER_LENGTH: usize = 24;
+const MARCXML_NAMESPACE: &str = "http://www.loc.gov/MARC21/slim";
+
+/// Leading articles that are skipped when the title is filed, for the second indicator of 245.
+const NONFILING_ARTICLES: [&str; 3] = ["The ", "An ", "A "];
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Field {
+    Control {
+        tag: String,
+        value: String,
+    },
+    Data {
+        tag: String,
+        indicators: [char; 2],
+        subfields: Vec<(char, String)>,
+    },
+}
+
+impl Field {
+    pub fn tag(&self) -> &str {
+        match self {
+            Field::Control { tag, .. } | Field::Data { tag, .. } => tag,
+        }
+    }
+
+    fn data(tag: &str, indicators: [char; 2], subfields: Vec<(char, String)>) -> Field {
+        Field::Data {
+            tag: tag.to_string(),
+            indicators,
+            subfields,
+        }
+    }
+
+    /// The field's contents in ISO 2709 form, including the field terminator.
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        match self {
+            Field::Control { value, .. } => bytes.extend_from_slice(value.as_bytes()),
+            Field::Data {
+                indicators,
+                subfields,
+                ..
+            } => {
+                bytes.extend(indicators.iter().map(|&i| i as u8));
+                for (code, value) in subfields {
+                    bytes.push(SUBFIELD_DELIMITER);
+                    bytes.push(*code as u8);
+                    bytes.extend_from_slice(value.as_bytes());
+                }
+            }
+        }
+        bytes.push(FIELD_TERMINATOR);
+        bytes
+    }
+}
+
+/// A MARC 21 bibliographic record.
+///
+/// Only the fields that can be filled from a `Book` are produced: the ID (001), fixed-length data
+/// (008), ISBN (020), main and added authors (100 and 700), title (245), edition (250),
+/// publication (264), pages (300) and tags as subjects (650).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+    pub fields: Vec<Field>,
+}
+
+impl From<&Book> for Record {
+    fn from(book: &Book) -> Self {
+        let mut fields = vec![
+            Field::Control {
+                tag: "001".to_string(),
+                value: book.id.to_string(),
+            },
+            Field::Control {
+                tag: "008".to_string(),
+                value: fixed_length_data(&book.date_published),
+            },
+        ];
+        if let Some(isbn) = &book.isbn {
+            fields.push(Field::data("020", [' ', ' '], vec![('a', isbn.clone())]));
+        }
+        let mut authors = book.authors.iter().map(|author| author.to_string());
+        if let Some(author) = authors.next() {
+            fields.push(Field::data("100", ['1', ' '], vec![('a', author)]));
+        }
+        let nonfiling = NONFILING_ARTICLES
+            .iter()
+            .find(|article| book.title.starts_with(*article))
+            .map_or(0, |article| article.len());
+        let main_entry = if book.authors.is_empty() { '0' } else { '1' };
+        fields.push(Field::data(
+            "245",
+            [main_entry, char::from_digit(nonfiling as u32, 10).unwrap()],
+            vec![('a', book.title.clone())],
+        ));
+        if !book.edition.is_empty() {
+            fields.push(Field::data(
+                "250",
+                [' ', ' '],
+                vec![('a', book.edition.clone())],
+            ));
+        }
+        let mut publication = Vec::new();
+        if !book.publisher.name.is_empty() {
+            publication.push(('b', book.publisher.name.clone()));
+        }
+        if !book.date_published.is_empty() {
+            publication.push(('c', book.date_published.clone()));
+        }
+        if !publication.is_empty() {
+            fields.push(Field::data("264", [' ', '1'], publication));
+        }
+        if book.pages > 0 {
+            fields.push(Field::data(
+                "300",
+                [' ', ' '],
+                vec![('a', format!("{} pages", book.pages))],
+            ));
+        }
+        // The tags are usually subjects from a metadata provider, but their source isn't known.
+        fields.extend(
+            book.tags
+                .iter()
+                .map(|tag| Field::data("650", [' ', '4'], vec![('a', tag.clone())])),
+        );
+        fields.extend(authors.map(|author| Field::data("700", ['1', ' '], vec![('a', author)])));
+        Record { fields }
+    }
+}
+
+impl Record {
+    /// The leader for a record of the given length whose data starts at the given address.
+    ///
+    /// The record is a complete, new bibliographic record for a monograph, with the data encoded
+    /// as UTF-8.
+    fn leader(record_length: usize, base_address: usize) -> String {
+        format!("{record_length:05}nam a22{base_address:05} i 4500")
+    }
+
+    /// The record in the ISO 2709 exchange format used for MARC 21.
+    pub fn to_marc21(&self) -> Vec<u8> {
+        let mut directory = Vec::new();
+        let mut data = Vec::new();
+        for field in self.fields.iter() {
+            let bytes = field.to_bytes();
+            // Each directory entry is the tag, the length and the starting position of the field.
+            directory.extend_from_slice(
+                format!("{}{:04}{:05}", field.tag(), bytes.len(), data.len()).as_bytes(),
+            );
+            data.extend(bytes);
+        }
+        directory.push(FIELD_TERMINATOR);
+
+        let base_address = LEADER_LENGTH + directory.len();
+        let record_length = base_address + data.len() + 1;
+        let mut record = Record::leader(record_length, base_address).into_bytes();
+        record.extend(directory);
+        record.extend(data);
+        record.push(RECORD_TERMINATOR);
+        record
+    }
+
+    /// The record as a MARCXML `record` element.
+    pub fn to_marcxml(&self) -> String {
+        // The leader is the same as for the binary record, so that it gives the same lengths.
+        let marc21 = self.to_marc21();
+        let leader = String::from_utf8_lossy(&marc21[..LEADER_LENGTH]);
+        let mut xml = String::from("  <record>\n");
+        xml.push_str(&format!("    <leader>{leader}</leader>\n"));
+        for field in self.fields.iter() {
+            match field {
+                Field::Control { tag, value } => xml.push_str(&format!(
+                    "    <controlfield tag=\"{tag}\">{}</controlfield>\n",
+                    escape_xml(value)
+                )),
+                Field::Data {
+                    tag,
+                    indicators,
+                    subfields,
+                } => {
+                    xml.push_str(&format!(
+                        "    <datafield tag=\"{tag}\" ind1=\"{}\" ind2=\"{}\">\n",
+                        indicators[0], indicators[1]
+                    ));
+                    for (code, value) in subfields {
+                        xml.push_str(&format!(
+                            "      <subfield code=\"{code}\">{}</subfield>\n",
+                            escape_xml(value)
+                        ));
+                    }
+                    xml.push_str("    </datafield>\n");
+                }
+            }
+        }
+        xml.push_str("  </record>\n");
+        xml
+    }
+}
+
+/// Write the books as consecutive MARC 21 records.
+pub fn write_marc21<W: Write>(books: &[Book], mut writer: W) -> Result<()> {
+    for book in books {
+        writer.write_all(&Record::from(book).to_marc21())?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write the books as a MARCXML collection.
+pub fn write_marcxml<W: Write>(books: &[Book], mut writer: W) -> Result<()> {
+    writeln!(writer, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+    writeln!(writer, "<collection xmlns=\"{MARCXML_NAMESPACE}\">")?;
+    for book in books {
+        write!(writer, "{}", Record::from(book).to_marcxml())?;
+    }
+    writeln!(writer, "</collection>")?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// The 40 characters of field 008. Only the publication year is known; the rest is left blank
+/// or marked as undetermined.
+fn fixed_length_data(date_published: &str) -> String {
+    let year: String = date_published.chars().take(4).collect();
+    let (date_type, year) = if year.len() == 4 && year.chars().all(|c| c.is_ascii_digit()) {
+        ('s', year)
+    } else {
+        ('n', "uuuu".to_string())
+    };
+    format!("      {date_type}{year}    xx {:17}und d", "")
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        write_marc21, write_marcxml, Field, Record, FIELD_TERMINATOR, LEADER_LENGTH,
+        MARCXML_NAMESPACE, RECORD_TERMINATOR, SUBFIELD_DELIMITER,
+    };
+    use crate::books::{Author, Book, Publisher};
+
+    fn book() -> Book {
+        Book {
+            id: 7,
+            authors: vec![
+                Author {
+                    id: 1,
+                    forename: "Jim".to_string(),
+                    surname: "Dwyer".to_string(),
+                },
+                Author {
+                    id: 2,
+                    forename: "Peg".to_string(),
+                    surname: "Tyre".to_string(),
+                },
+            ],
+            publisher: Publisher {
+                id: 1,
+                name: "Crown & Sons".to_string(),
+            },
+            title: "The Conspiracy Behind the World Trade Center Bombing".to_string(),
+            edition: "1st".to_string(),
+            date_published: "1994".to_string(),
+            binding: "Hardcover".to_string(),
+            isbn: Some("9780517597675".to_string()),
+            pages: 322,
+            owned: true,
+            tags: vec!["Terrorism".to_string()],
+            ..Default::default()
+        }
+    }
+
+    /// Read ISO 2709 records using only the leader and directory, independently of the writer.
+    fn parse_marc21(mut bytes: &[u8]) -> Vec<(String, Record)> {
+        let mut records = Vec::new();
+        while !bytes.is_empty() {
+            let leader = std::str::from_utf8(&bytes[..LEADER_LENGTH]).unwrap();
+            let length: usize = leader[0..5].parse().unwrap();
+            let base_address: usize = leader[12..17].parse().unwrap();
+            let (record, rest) = bytes.split_at(length);
+            assert_eq!(record[length - 1], RECORD_TERMINATOR);
+            assert_eq!(record[base_address - 1], FIELD_TERMINATOR);
+
+            let directory = &record[LEADER_LENGTH..base_address - 1];
+            let fields = directory
+                .chunks(12)
+                .map(|entry| {
+                    let entry = std::str::from_utf8(entry).unwrap();
+                    let tag = entry[0..3].to_string();
+                    let length: usize = entry[3..7].parse().unwrap();
+                    let start: usize = entry[7..12].parse().unwrap();
+                    let data = &record[base_address + start..base_address + start + length];
+                    assert_eq!(data[length - 1], FIELD_TERMINATOR);
+                    let data = std::str::from_utf8(&data[..length - 1]).unwrap();
+                    if tag.starts_with("00") {
+                        return Field::Control {
+                            tag,
+                            value: data.to_string(),
+                        };
+                    }
+                    let mut indicators = data.chars().take(2);
+                    let indicators = [indicators.next().unwrap(), indicators.next().unwrap()];
+                    let subfields = data[2..]
+                        .split(SUBFIELD_DELIMITER as char)
+                        .skip(1)
+                        .map(|subfield| {
+                            let mut chars = subfield.chars();
+                            let code = chars.next().unwrap();
+                            (code, chars.as_str().to_string())
+                        })
+                        .collect();
+                    Field::Data {
+                        tag,
+                        indicators,
+                        subfields,
+                    }
+                })
+                .collect();
+            records.push((leader.to_string(), Record { fields }));
+            bytes = rest;
+        }
+        records
+    }
+
+    fn parse_marcxml(xml: &str) -> Vec<(String, Record)> {
+        let document = roxmltree::Document::parse(xml).unwrap();
+        let collection = document.root_element();
+        assert_eq!(collection.tag_name().namespace(), Some(MARCXML_NAMESPACE));
+        collection
+            .children()
+            .filter(|node| node.has_tag_name("record"))
+            .map(|record| {
+                let mut leader = String::new();
+                let mut fields = Vec::new();
+                for node in record.children().filter(|node| node.is_element()) {
+                    let tag = node.attribute("tag").unwrap_or_default().to_string();
+                    match node.tag_name().name() {
+                        "leader" => leader = node.text().unwrap().to_string(),
+                        "controlfield" => fields.push(Field::Control {
+                            tag,
+                            value: node.text().unwrap().to_string(),
+                        }),
+                        "datafield" => {
+                            let indicator = |name| node.attribute(name).unwrap().chars().next();
+                            fields.push(Field::Data {
+                                tag,
+                                indicators: [
+                                    indicator("ind1").unwrap(),
+                                    indicator("ind2").unwrap(),
+                                ],
+                                subfields: node
+                                    .children()
+                                    .filter(|node| node.has_tag_name("subfield"))
+                                    .map(|subfield| {
+                                        let code = subfield.attribute("code").unwrap();
+                                        (
+                                            code.chars().next().unwrap(),
+                                            subfield.text().unwrap().to_string(),
+                                        )
+                                    })
+                                    .collect(),
+                            })
+                        }
+                        name => panic!("Unexpected element {name}"),
+                    }
+                }
+                (leader, Record { fields })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn record_should_map_the_book_fields_to_marc_tags() {
+        let record = Record::from(&book());
+        let tags: Vec<&str> = record.fields.iter().map(|field| field.tag()).collect();
+        assert_eq!(
+            tags,
+            vec!["001", "008", "020", "100", "245", "250", "264", "300", "650", "700"]
+        );
+        assert_eq!(
+            record.fields[4],
+            Field::Data {
+                tag: "245".to_string(),
+                indicators: ['1', '4'],
+                subfields: vec![(
+                    'a',
+                    "The Conspiracy Behind the World Trade Center Bombing".to_string()
+                )],
+            }
+        );
+        assert_eq!(
+            record.fields[6],
+            Field::Data {
+                tag: "264".to_string(),
+                indicators: [' ', '1'],
+                subfields: vec![('b', "Crown & Sons".to_string()), ('c', "1994".to_string())],
+            }
+        );
+        match &record.fields[1] {
+            Field::Control { value, .. } => {
+                assert_eq!(value.len(), 40);
+                assert_eq!(&value[6..11], "s1994");
+            }
+            field => panic!("Expected a control field, not {field:?}"),
+        }
+    }
+
+    #[test]
+    fn marc21_export_should_round_trip() {
+        let mut without_isbn = book();
+        without_isbn.id = 8;
+        without_isbn.isbn = None;
+        without_isbn.authors.truncate(1);
+        without_isbn.title = "Ünter den Linden".to_string();
+        let books = vec![book(), without_isbn];
+
+        let mut output = Vec::new();
+        write_marc21(&books, &mut output).unwrap();
+        let records = parse_marc21(&output);
+
+        assert_eq!(records.len(), 2);
+        for ((leader, record), book) in records.iter().zip(books.iter()) {
+            assert_eq!(&leader[5..10], "nam a");
+            assert_eq!(&leader[20..24], "4500");
+            assert_eq!(*record, Record::from(book));
+        }
+    }
+
+    /// The records for `book()` in `resources` were written by hand from the MARC 21
+    /// specification rather than by this module.
+    #[test]
+    fn marc21_export_should_match_a_known_good_record() {
+        let mut output = Vec::new();
+        write_marc21(&[book()], &mut output).unwrap();
+        let expected =
+            std::fs::read(std::path::Path::new("resources").join("marc21_record.mrc")).unwrap();
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn marcxml_export_should_match_a_known_good_record() {
+        let mut output = Vec::new();
+        write_marcxml(&[book()], &mut output).unwrap();
+        let expected =
+            std::fs::read_to_string(std::path::Path::new("resources").join("marcxml_record.xml"))
+                .unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
+    }
+
+    #[test]
+    fn marcxml_export_should_round_trip() {
+        let books = vec![book()];
+        let mut output = Vec::new();
+        write_marcxml(&books, &mut output).unwrap();
+        let records = parse_marcxml(&String::from_utf8(output).unwrap());
+
+        let mut marc21 = Vec::new();
+        write_marc21(&books, &mut marc21).unwrap();
+        let (marc21_leader, _) = &parse_marc21(&marc21)[0];
+
+        assert_eq!(records.len(), 1);
+        let (leader, record) = &records[0];
+        assert_eq!(leader, marc21_leader);
+        assert_eq!(*record, Record::from(&books[0]));
+    }
+}
@@ -0,0 +1,380 @@
+use crate::error::{Error, Result};
+use async_trait::async_trait;
+use clap::ValueEnum;
+use prettytable::{Cell, Row, Table};
+use serde_derive::Deserialize;
+
+const WRAP_LENGTH: usize = 80;
+const LIST_WRAP_LENGTH: usize = 50;
+
+/// The metadata providers that can be used to look up books.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, ValueEnum)]
+pub enum ProviderKind {
+    #[default]
+    #[serde(rename = "isbndb")]
+    #[value(name = "isbndb")]
+    IsbnDb,
+    #[serde(rename = "open-library")]
+    OpenLibrary,
+    #[serde(rename = "google-books")]
+    GoogleBooks,
+}
+
+/// The details of a book as supplied by a metadata provider.
+///
+/// Providers vary in what they know about a book, so everything other than the title is
+/// optional. Authors are given in the "Surname, Forename" form used by the rest of the
+/// application.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BookRecord {
+    pub title: String,
+    pub title_long: Option<String>,
+    pub authors: Vec<String>,
+    pub publisher: Option<String>,
+    pub edition: Option<String>,
+    pub date_published: Option<String>,
+    pub binding: Option<String>,
+    pub pages: Option<u32>,
+    pub isbn13: Option<String>,
+    pub isbn10: Option<String>,
+    pub language: Option<String>,
+    pub subjects: Vec<String>,
+    pub synopsis: Option<String>,
+    pub image_url: Option<String>,
+    pub dimensions: Option<String>,
+    pub msrp: Option<f64>,
+}
+
+/// Criteria for searching a provider for books.
+///
+/// At least one of the fields should be set. When both are, a book must match both.
+#[derive(Clone, Debug, Default)]
+pub struct SearchQuery {
+    pub title: Option<String>,
+    pub author: Option<String>,
+}
+
+impl SearchQuery {
+    /// Determine whether a record satisfies the query, ignoring case.
+    ///
+    /// Providers that can only search on one field at a time use this to apply the other.
+    pub fn matches(&self, record: &BookRecord) -> bool {
+        if let Some(title) = &self.title {
+            let title = title.to_lowercase();
+            let long_title = record.title_long.as_deref().unwrap_or("");
+            if !record.title.to_lowercase().contains(&title)
+                && !long_title.to_lowercase().contains(&title)
+            {
+                return false;
+            }
+        }
+        if let Some(author) = &self.author {
+            let author = author.to_lowercase();
+            if !record
+                .authors
+                .iter()
+                .any(|a| a.to_lowercase().contains(&author))
+            {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// A source of book details.
+#[async_trait]
+pub trait MetadataProvider: Send + Sync {
+    /// Look up a single book by its ISBN.
+    async fn lookup_by_isbn(&self, isbn: &str) -> Result<BookRecord>;
+
+    /// Look up many books by their ISBNs.
+    ///
+    /// The results are in the same order as the ISBNs, with `None` for the books the provider has
+    /// no record of. By default the books are looked up one at a time; providers that can look up
+    /// several in a request override this.
+    async fn lookup_by_isbns(&self, isbns: &[String]) -> Result<Vec<(String, Option<BookRecord>)>> {
+        let mut results = Vec::new();
+        for isbn in isbns {
+            match self.lookup_by_isbn(isbn).await {
+                Ok(record) => results.push((isbn.clone(), Some(record))),
+                Err(Error::RecordNotFound { .. }) => results.push((isbn.clone(), None)),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(results)
+    }
+
+    /// Search for books by title, author, or both.
+    async fn search(&self, query: &SearchQuery) -> Result<Vec<BookRecord>>;
+}
+
+impl BookRecord {
+    pub fn print(&self) {
+        let mut table = Table::new();
+        let wrapped_title = textwrap::wrap(&self.title, WRAP_LENGTH).join("\n");
+        table.add_row(Row::new(vec![
+            Cell::new("Title"),
+            Cell::new(&wrapped_title),
+        ]));
+        if let Some(title_long) = &self.title_long {
+            if *title_long != self.title {
+                let wrapped_long_title = textwrap::wrap(title_long, 72).join("\n");
+                table.add_row(Row::new(vec![
+                    Cell::new("Title (Long)"),
+                    Cell::new(&wrapped_long_title),
+                ]));
+            }
+        }
+        table.add_row(Row::new(vec![
+            Cell::new("Author(s)"),
+            Cell::new(&self.authors.join("; ")),
+        ]));
+        table.add_row(Row::new(vec![
+            Cell::new("Date Published"),
+            Cell::new(or_na(&self.date_published)),
+        ]));
+        table.add_row(Row::new(vec![
+            Cell::new("Binding"),
+            Cell::new(or_na(&self.binding)),
+        ]));
+        table.add_row(Row::new(vec![
+            Cell::new("Edition"),
+            Cell::new(or_na(&self.edition)),
+        ]));
+        table.add_row(Row::new(vec![
+            Cell::new("Pages"),
+            Cell::new(&self.pages.map_or("N/A".to_string(), |p| p.to_string())),
+        ]));
+        table.add_row(Row::new(vec![
+            Cell::new("Publisher"),
+            Cell::new(or_na(&self.publisher)),
+        ]));
+        table.add_row(Row::new(vec![
+            Cell::new("Language"),
+            Cell::new(or_na(&self.language)),
+        ]));
+
+        let subjects = if self.subjects.is_empty() {
+            "N/A".to_string()
+        } else {
+            self.subjects.join(", ")
+        };
+        let wrapped_subjects = textwrap::wrap(&subjects, WRAP_LENGTH).join("\n");
+        table.add_row(Row::new(vec![
+            Cell::new("Subjects"),
+            Cell::new(&wrapped_subjects),
+        ]));
+
+        let wrapped_synopsis = textwrap::wrap(or_na(&self.synopsis), WRAP_LENGTH).join("\n");
+        table.add_row(Row::new(vec![
+            Cell::new("Synopsis"),
+            Cell::new(&wrapped_synopsis),
+        ]));
+        table.add_row(Row::new(vec![
+            Cell::new("MSRP"),
+            Cell::new(&self.msrp.map_or("N/A".to_string(), |msrp| msrp.to_string())),
+        ]));
+        table.add_row(Row::new(vec![
+            Cell::new("ISBN13"),
+            Cell::new(or_na(&self.isbn13)),
+        ]));
+        table.add_row(Row::new(vec![
+            Cell::new("ISBN10"),
+            Cell::new(or_na(&self.isbn10)),
+        ]));
+        table.add_row(Row::new(vec![
+            Cell::new("Dimensions"),
+            Cell::new(or_na(&self.dimensions)),
+        ]));
+        table.printstd();
+    }
+}
+
+pub fn print_records(records: &[BookRecord]) {
+    let mut table = Table::new();
+    table.set_titles(Row::new(vec![
+        Cell::new("Title"),
+        Cell::new("Author(s)"),
+        Cell::new("Publisher"),
+        Cell::new("Date Published"),
+        Cell::new("ISBN"),
+    ]));
+    for record in records.iter() {
+        let wrapped_title = textwrap::wrap(&record.title, LIST_WRAP_LENGTH).join("\n");
+        let isbn = record.isbn13.as_ref().or(record.isbn10.as_ref()).cloned();
+        table.add_row(Row::new(vec![
+            Cell::new(&wrapped_title),
+            Cell::new(&record.authors.join("; ")),
+            Cell::new(or_na(&record.publisher)),
+            Cell::new(or_na(&record.date_published)),
+            Cell::new(or_na(&isbn)),
+        ]));
+    }
+    table.printstd();
+}
+
+/// Convert a name written as "Forename Surname" to the "Surname, Forename" form.
+///
+/// The last word is taken as the surname. Names that already contain a comma, or that are a
+/// single word, are returned as they are.
+pub fn surname_first(name: &str) -> String {
+    let name = name.trim();
+    if name.contains(',') {
+        return name.to_string();
+    }
+    match name.rsplit_once(' ') {
+        Some((forename, surname)) => format!("{}, {}", surname, forename.trim()),
+        None => name.to_string(),
+    }
+}
+
+fn or_na(value: &Option<String>) -> &str {
+    value.as_deref().unwrap_or("N/A")
+}
+
+/// An in-process provider for use in tests.
+#[cfg(test)]
+pub mod fake {
+    use super::{BookRecord, MetadataProvider, SearchQuery};
+    use crate::error::{Error, Result};
+    use async_trait::async_trait;
+
+    #[derive(Default)]
+    pub struct FakeProvider {
+        pub records: Vec<BookRecord>,
+    }
+
+    #[async_trait]
+    impl MetadataProvider for FakeProvider {
+        async fn lookup_by_isbn(&self, isbn: &str) -> Result<BookRecord> {
+            self.records
+                .iter()
+                .find(|r| r.isbn13.as_deref() == Some(isbn) || r.isbn10.as_deref() == Some(isbn))
+                .cloned()
+                .ok_or_else(|| Error::RecordNotFound {
+                    provider: "Fake".to_string(),
+                    isbn: isbn.to_string(),
+                })
+        }
+
+        async fn search(&self, query: &SearchQuery) -> Result<Vec<BookRecord>> {
+            Ok(self
+                .records
+                .iter()
+                .filter(|r| query.matches(r))
+                .cloned()
+                .collect())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::fake::FakeProvider;
+    use super::{surname_first, BookRecord, MetadataProvider, SearchQuery};
+    use crate::models::AddBookModel;
+    use color_eyre::Result;
+
+    fn records() -> Vec<BookRecord> {
+        vec![
+            BookRecord {
+                title: "The New Jackals".to_string(),
+                title_long: Some(
+                    "The New Jackals: Osama Bin Laden and the Future of Terrorism".to_string(),
+                ),
+                authors: vec!["Reeve, Simon".to_string()],
+                publisher: Some("Carlton Publishing Group".to_string()),
+                edition: Some("2nd".to_string()),
+                date_published: Some("2001".to_string()),
+                binding: Some("Paperback".to_string()),
+                pages: Some(352),
+                isbn13: Some("9780233050485".to_string()),
+                isbn10: Some("0233050485".to_string()),
+                ..Default::default()
+            },
+            BookRecord {
+                title: "Two Seconds Under the World".to_string(),
+                authors: vec!["Dwyer, Jim".to_string(), "Murphy, Deidre".to_string()],
+                publisher: Some("Crown".to_string()),
+                edition: Some("1st".to_string()),
+                date_published: Some("1997".to_string()),
+                isbn13: Some("9780517597675".to_string()),
+                ..Default::default()
+            },
+        ]
+    }
+
+    #[tokio::test]
+    async fn lookup_by_isbn_should_return_a_record_that_converts_to_an_add_book_model() -> Result<()>
+    {
+        let provider: Box<dyn MetadataProvider> = Box::new(FakeProvider { records: records() });
+
+        let record = provider.lookup_by_isbn("9780517597675").await?;
+        let model = AddBookModel::from(record);
+
+        assert_eq!(model.title, "Two Seconds Under the World");
+        assert_eq!(model.authors, "Dwyer, Jim; Murphy, Deidre");
+        assert_eq!(model.publisher, "Crown");
+        assert_eq!(model.original_date_published, Some("1997".to_string()));
+        // Fields the provider didn't supply are left empty for the user to fill in.
+        assert_eq!(model.binding, "");
+        assert_eq!(model.pages, 0);
+        assert_eq!(model.isbn, Some("9780517597675".to_string()));
+        assert!(provider.lookup_by_isbn("9780000000000").await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn lookup_by_isbns_should_look_up_each_book_and_report_missing_books() -> Result<()> {
+        let provider = FakeProvider { records: records() };
+
+        let isbns = vec!["9780517597675".to_string(), "9780000000002".to_string()];
+        let results = provider.lookup_by_isbns(&isbns).await?;
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].0, "9780517597675");
+        assert_eq!(
+            results[0].1.as_ref().map(|r| r.title.as_str()),
+            Some("Two Seconds Under the World")
+        );
+        assert_eq!(results[1], ("9780000000002".to_string(), None));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_should_match_on_title_and_author() -> Result<()> {
+        let provider = FakeProvider { records: records() };
+
+        let query = SearchQuery {
+            title: Some("osama".to_string()),
+            author: None,
+        };
+        let found = provider.search(&query).await?;
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].title, "The New Jackals");
+
+        let query = SearchQuery {
+            title: Some("world".to_string()),
+            author: Some("murphy".to_string()),
+        };
+        let found = provider.search(&query).await?;
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].publisher, Some("Crown".to_string()));
+
+        let query = SearchQuery {
+            title: Some("world".to_string()),
+            author: Some("reeve".to_string()),
+        };
+        assert!(provider.search(&query).await?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn surname_first_should_reorder_forename_and_surname() {
+        assert_eq!(surname_first("Jim Dwyer"), "Dwyer, Jim");
+        assert_eq!(surname_first("J. R. R. Tolkien"), "Tolkien, J. R. R.");
+        assert_eq!(surname_first("Dwyer, Jim"), "Dwyer, Jim");
+        assert_eq!(surname_first("Homer"), "Homer");
+    }
+}
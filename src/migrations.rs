@@ -0,0 +1,442 @@
+use crate::error::{Error, Result};
+use rusqlite::Connection;
+
+/// The schema migrations, in the order they are applied.
+///
+/// The version of a database's schema is the number of migrations that have been applied to it,
+/// which is recorded in SQLite's `user_version` pragma. Migrations must never be edited or
+/// reordered once released; schema changes are made by appending a new migration.
+const MIGRATIONS: &[&str] = &[
+    // 1: The initial tables. These were previously created on demand, so databases that predate
+    // the migrations will already have them.
+    "
+    CREATE TABLE IF NOT EXISTS publishers (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        name TEXT NOT NULL UNIQUE
+    );
+
+    CREATE TABLE IF NOT EXISTS authors (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        forename TEXT NOT NULL,
+        surname TEXT NOT NULL,
+        UNIQUE(forename, surname)
+    );
+
+    CREATE TABLE IF NOT EXISTS books (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        publisher_id INTEGER,
+        title TEXT NOT NULL,
+        edition TEXT,
+        date_published TEXT NOT NULL,
+        original_date_published TEXT,
+        price DECIMAL,
+        binding TEXT NOT NULL,
+        isbn TEXT NOT NULL,
+        pages INTEGER NOT NULL DEFAULT 0,
+        owned INTEGER NOT NULL DEFAULT 0,
+        UNIQUE(title, edition),
+        FOREIGN KEY (publisher_id) REFERENCES publishers(id)
+    );
+
+    CREATE TABLE IF NOT EXISTS books_authors (
+        book_id INTEGER,
+        author_id INTEGER,
+        FOREIGN KEY (book_id) REFERENCES books(id),
+        FOREIGN KEY (author_id) REFERENCES authors(id)
+    );
+    ",
+    // 2: The full-text search index. Its row IDs are the IDs of the books it indexes, and triggers
+    // keep it in sync with the tables it's derived from.
+    "
+    CREATE VIRTUAL TABLE IF NOT EXISTS books_fts USING fts5(title, authors, publisher);
+
+    CREATE TRIGGER IF NOT EXISTS books_fts_book_insert AFTER INSERT ON books BEGIN
+        INSERT INTO books_fts (rowid, title, authors, publisher)
+        VALUES (
+            new.id,
+            new.title,
+            '',
+            COALESCE((SELECT name FROM publishers WHERE id = new.publisher_id), '')
+        );
+    END;
+
+    CREATE TRIGGER IF NOT EXISTS books_fts_book_update AFTER UPDATE ON books BEGIN
+        UPDATE books_fts SET
+            title = new.title,
+            publisher = COALESCE(
+                (SELECT name FROM publishers WHERE id = new.publisher_id), '')
+        WHERE rowid = new.id;
+    END;
+
+    CREATE TRIGGER IF NOT EXISTS books_fts_book_delete AFTER DELETE ON books BEGIN
+        DELETE FROM books_fts WHERE rowid = old.id;
+    END;
+
+    CREATE TRIGGER IF NOT EXISTS books_fts_book_author_insert
+    AFTER INSERT ON books_authors BEGIN
+        UPDATE books_fts SET authors = (
+            SELECT COALESCE(group_concat(authors.forename || ' ' || authors.surname, '; '), '')
+            FROM authors
+            JOIN books_authors ON authors.id = books_authors.author_id
+            WHERE books_authors.book_id = new.book_id
+        )
+        WHERE rowid = new.book_id;
+    END;
+
+    CREATE TRIGGER IF NOT EXISTS books_fts_book_author_delete
+    AFTER DELETE ON books_authors BEGIN
+        UPDATE books_fts SET authors = (
+            SELECT COALESCE(group_concat(authors.forename || ' ' || authors.surname, '; '), '')
+            FROM authors
+            JOIN books_authors ON authors.id = books_authors.author_id
+            WHERE books_authors.book_id = old.book_id
+        )
+        WHERE rowid = old.book_id;
+    END;
+
+    CREATE TRIGGER IF NOT EXISTS books_fts_author_update AFTER UPDATE ON authors BEGIN
+        UPDATE books_fts SET authors = (
+            SELECT COALESCE(group_concat(authors.forename || ' ' || authors.surname, '; '), '')
+            FROM authors
+            JOIN books_authors ON authors.id = books_authors.author_id
+            WHERE books_authors.book_id = books_fts.rowid
+        )
+        WHERE rowid IN (SELECT book_id FROM books_authors WHERE author_id = new.id);
+    END;
+
+    CREATE TRIGGER IF NOT EXISTS books_fts_publisher_update
+    AFTER UPDATE ON publishers BEGIN
+        UPDATE books_fts SET publisher = new.name
+        WHERE rowid IN (SELECT id FROM books WHERE publisher_id = new.id);
+    END;
+
+    DELETE FROM books_fts;
+    INSERT INTO books_fts (rowid, title, authors, publisher)
+    SELECT
+        books.id,
+        books.title,
+        COALESCE((
+            SELECT group_concat(authors.forename || ' ' || authors.surname, '; ')
+            FROM authors
+            JOIN books_authors ON authors.id = books_authors.author_id
+            WHERE books_authors.book_id = books.id
+        ), ''),
+        COALESCE(publishers.name, '')
+    FROM books
+    LEFT JOIN publishers ON books.publisher_id = publishers.id;
+    ",
+    // 3: Allow books without an ISBN. SQLite can't drop a NOT NULL constraint, so the table is
+    // rebuilt. Dropping the table also drops its search index triggers, and the publisher trigger
+    // has to go while the table doesn't exist, because it refers to it.
+    "
+    CREATE TABLE books_new (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        publisher_id INTEGER,
+        title TEXT NOT NULL,
+        edition TEXT,
+        date_published TEXT NOT NULL,
+        original_date_published TEXT,
+        price DECIMAL,
+        binding TEXT NOT NULL,
+        isbn TEXT,
+        pages INTEGER NOT NULL DEFAULT 0,
+        owned INTEGER NOT NULL DEFAULT 0,
+        UNIQUE(title, edition),
+        FOREIGN KEY (publisher_id) REFERENCES publishers(id)
+    );
+
+    INSERT INTO books_new (
+        id, publisher_id, title, edition, date_published, original_date_published,
+        price, binding, isbn, pages, owned
+    )
+    SELECT
+        id, publisher_id, title, edition, date_published, original_date_published,
+        price, binding, isbn, pages, owned
+    FROM books;
+
+    DROP TRIGGER books_fts_publisher_update;
+    DROP TABLE books;
+    ALTER TABLE books_new RENAME TO books;
+
+    CREATE TRIGGER books_fts_publisher_update AFTER UPDATE ON publishers BEGIN
+        UPDATE books_fts SET publisher = new.name
+        WHERE rowid IN (SELECT id FROM books WHERE publisher_id = new.id);
+    END;
+
+    CREATE TRIGGER books_fts_book_insert AFTER INSERT ON books BEGIN
+        INSERT INTO books_fts (rowid, title, authors, publisher)
+        VALUES (
+            new.id,
+            new.title,
+            '',
+            COALESCE((SELECT name FROM publishers WHERE id = new.publisher_id), '')
+        );
+    END;
+
+    CREATE TRIGGER books_fts_book_update AFTER UPDATE ON books BEGIN
+        UPDATE books_fts SET
+            title = new.title,
+            publisher = COALESCE(
+                (SELECT name FROM publishers WHERE id = new.publisher_id), '')
+        WHERE rowid = new.id;
+    END;
+
+    CREATE TRIGGER books_fts_book_delete AFTER DELETE ON books BEGIN
+        DELETE FROM books_fts WHERE rowid = old.id;
+    END;
+    ",
+    // 4: A cache of the raw responses from metadata providers, so looking up a book again doesn't
+    // spend another API call.
+    "
+    CREATE TABLE provider_cache (
+        provider TEXT NOT NULL,
+        isbn TEXT NOT NULL,
+        response TEXT NOT NULL,
+        fetched_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        PRIMARY KEY (provider, isbn)
+    );
+    ",
+    // 5: Reading history, as kept by Goodreads and similar sites.
+    "
+    ALTER TABLE books ADD COLUMN rating INTEGER;
+    ALTER TABLE books ADD COLUMN shelf TEXT;
+    ALTER TABLE books ADD COLUMN date_read TEXT;
+    ",
+    // 6: Tags, such as the subjects given by metadata providers. Names are unique regardless of
+    // case, so "History" and "history" are the same tag.
+    "
+    CREATE TABLE tags (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        name TEXT NOT NULL UNIQUE COLLATE NOCASE
+    );
+
+    CREATE TABLE books_tags (
+        book_id INTEGER NOT NULL,
+        tag_id INTEGER NOT NULL,
+        PRIMARY KEY (book_id, tag_id),
+        FOREIGN KEY (book_id) REFERENCES books(id),
+        FOREIGN KEY (tag_id) REFERENCES tags(id)
+    );
+    ",
+    // 7: The rest of the details given by ISBNdb.
+    "
+    ALTER TABLE books ADD COLUMN short_title TEXT;
+    ALTER TABLE books ADD COLUMN synopsis TEXT;
+    ALTER TABLE books ADD COLUMN language TEXT;
+    ALTER TABLE books ADD COLUMN dimensions TEXT;
+    ALTER TABLE books ADD COLUMN image_url TEXT;
+    ALTER TABLE books ADD COLUMN msrp DECIMAL;
+    ",
+    // 8: Downloaded cover images. The path is relative to the directory holding the database.
+    "
+    ALTER TABLE books ADD COLUMN cover_path TEXT;
+    ALTER TABLE books ADD COLUMN cover_hash TEXT;
+    ",
+    // 9: The ISBN-10 as given by the metadata provider.
+    "
+    ALTER TABLE books ADD COLUMN isbn10 TEXT;
+    ",
+];
+
+/// The schema version this build of the application works with.
+pub const SCHEMA_VERSION: usize = MIGRATIONS.len();
+
+/// Apply any migrations that have not yet been applied to the database.
+///
+/// Each migration runs in its own transaction along with the update to the schema version, so a
+/// failure leaves the database at the last version that was successfully applied. A database
+/// whose schema is newer than this build knows about is refused rather than risk corrupting it.
+pub fn migrate(conn: &mut Connection) -> Result<()> {
+    let version = schema_version(conn)?;
+    if version > SCHEMA_VERSION {
+        return Err(Error::SchemaTooNew {
+            found: version,
+            supported: SCHEMA_VERSION,
+        });
+    }
+    if version == SCHEMA_VERSION {
+        return Ok(());
+    }
+
+    // Migrations that rebuild a table would fall foul of the foreign key constraints that refer to
+    // it, so enforcement must be off. It can't be changed inside a transaction.
+    conn.pragma_update(None, "foreign_keys", "OFF")?;
+    let tx = conn.transaction()?;
+    remove_dangling_rows(&tx)?;
+    tx.commit()?;
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", index + 1)?;
+        let violations: usize =
+            tx.query_row("SELECT COUNT(*) FROM pragma_foreign_key_check", [], |row| {
+                row.get(0)
+            })?;
+        if violations > 0 {
+            return Err(Error::MigrationFailed {
+                version: index + 1,
+                reason: format!("{violations} rows violate foreign key constraints"),
+            });
+        }
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+/// Remove the references to books, authors, publishers and tags that no longer exist.
+///
+/// Foreign keys weren't always enforced, so an older database can have authors linked to a book
+/// that has since been removed. The links are meaningless, but left in place they would fail the
+/// check made after each migration, and the database could never be opened again.
+fn remove_dangling_rows(conn: &Connection) -> Result<()> {
+    let dangling = conn
+        .prepare("SELECT \"table\", rowid FROM pragma_foreign_key_check")?
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<(String, i64)>>>()?;
+    for (table, rowid) in dangling {
+        match table.as_str() {
+            // A book keeps its other details when its publisher is missing.
+            "books" => conn.execute(
+                "UPDATE books SET publisher_id = NULL WHERE rowid = ?1",
+                [rowid],
+            )?,
+            table => conn.execute(&format!("DELETE FROM {table} WHERE rowid = ?1"), [rowid])?,
+        };
+    }
+    Ok(())
+}
+
+pub fn schema_version(conn: &Connection) -> Result<usize> {
+    let version: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    Ok(version)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::search_books;
+    use assert_fs::prelude::*;
+
+    #[test]
+    fn migrate_should_bring_a_new_database_to_the_latest_version() {
+        let storage_dir = assert_fs::TempDir::new().unwrap();
+        let books_db_file = storage_dir.child("books.db");
+        let mut conn = Connection::open(books_db_file.path()).unwrap();
+
+        migrate(&mut conn).unwrap();
+
+        assert_eq!(schema_version(&conn).unwrap(), SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn migrate_should_upgrade_a_v1_database_and_keep_its_data() {
+        let storage_dir = assert_fs::TempDir::new().unwrap();
+        let books_db_file = storage_dir.child("books.db");
+        let fixture =
+            std::fs::read_to_string(std::path::Path::new("resources").join("books_v1.sql"))
+                .unwrap();
+        let mut conn = Connection::open(books_db_file.path()).unwrap();
+        conn.execute_batch(&fixture).unwrap();
+        assert_eq!(schema_version(&conn).unwrap(), 1);
+
+        migrate(&mut conn).unwrap();
+
+        assert_eq!(schema_version(&conn).unwrap(), SCHEMA_VERSION);
+        let book_count: u32 = conn
+            .query_row("SELECT COUNT(*) FROM books", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(book_count, 2);
+        let isbn: Option<String> = conn
+            .query_row("SELECT isbn FROM books WHERE id = 2", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(isbn, Some("9780517597675".to_string()));
+
+        // Books that existed before the upgrade should be in the search index.
+        let results = search_books(books_db_file.to_path_buf(), "kocieniewski", "", "").unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].publisher, "Crown");
+    }
+
+    #[test]
+    fn migrate_should_adopt_a_database_created_before_versioning() {
+        let storage_dir = assert_fs::TempDir::new().unwrap();
+        let books_db_file = storage_dir.child("books.db");
+        let fixture =
+            std::fs::read_to_string(std::path::Path::new("resources").join("books_v1.sql"))
+                .unwrap();
+        let mut conn = Connection::open(books_db_file.path()).unwrap();
+        conn.execute_batch(&fixture).unwrap();
+        conn.pragma_update(None, "user_version", 0).unwrap();
+
+        migrate(&mut conn).unwrap();
+
+        assert_eq!(schema_version(&conn).unwrap(), SCHEMA_VERSION);
+        let book_count: u32 = conn
+            .query_row("SELECT COUNT(*) FROM books", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(book_count, 2);
+    }
+
+    #[test]
+    fn migrate_should_remove_references_to_rows_that_no_longer_exist() {
+        let storage_dir = assert_fs::TempDir::new().unwrap();
+        let books_db_file = storage_dir.child("books.db");
+        let fixture =
+            std::fs::read_to_string(std::path::Path::new("resources").join("books_v1.sql"))
+                .unwrap();
+        let mut conn = Connection::open(books_db_file.path()).unwrap();
+        conn.execute_batch(&fixture).unwrap();
+        conn.pragma_update(None, "user_version", 0).unwrap();
+        // Rows like these could be left by versions that didn't enforce foreign keys.
+        conn.pragma_update(None, "foreign_keys", "OFF").unwrap();
+        conn.execute_batch(
+            "
+            INSERT INTO books_authors (book_id, author_id) VALUES (99, 1);
+            INSERT INTO books_authors (book_id, author_id) VALUES (1, 99);
+            UPDATE books SET publisher_id = 99 WHERE id = 1;
+            ",
+        )
+        .unwrap();
+
+        migrate(&mut conn).unwrap();
+
+        assert_eq!(schema_version(&conn).unwrap(), SCHEMA_VERSION);
+        let link_count: u32 = conn
+            .query_row("SELECT COUNT(*) FROM books_authors", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(link_count, 5);
+        let (title, publisher_id): (String, Option<i64>) = conn
+            .query_row(
+                "SELECT title, publisher_id FROM books WHERE id = 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert!(title.starts_with("The New Jackals"));
+        assert_eq!(publisher_id, None);
+        drop(conn);
+
+        // The database can be opened and used as normal.
+        let results = search_books(books_db_file.to_path_buf(), "reeve", "", "").unwrap();
+        assert_eq!(results.len(), 1);
+    }
+
+    #[test]
+    fn migrate_should_refuse_a_database_with_a_newer_schema() {
+        let storage_dir = assert_fs::TempDir::new().unwrap();
+        let books_db_file = storage_dir.child("books.db");
+        let mut conn = Connection::open(books_db_file.path()).unwrap();
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION + 1)
+            .unwrap();
+
+        let result = migrate(&mut conn);
+
+        assert!(matches!(
+            result,
+            Err(Error::SchemaTooNew { found, supported })
+                if found == SCHEMA_VERSION + 1 && supported == SCHEMA_VERSION
+        ));
+    }
+}
@@ -1,5 +1,7 @@
+use crate::books::Book;
 use crate::error::Error;
 use crate::isbn_db::IsbnDbBook;
+use crate::metadata::BookRecord;
 use std::str::FromStr;
 
 #[derive(Debug)]
@@ -12,31 +14,98 @@ pub struct AddBookModel {
     pub original_date_published: Option<String>,
     pub price: Option<f64>,
     pub binding: String,
-    pub isbn: String,
+    pub isbn: Option<String>,
     pub pages: u32,
     pub owned: bool,
+    pub rating: Option<u8>,
+    pub shelf: Option<String>,
+    pub date_read: Option<String>,
+    pub tags: Vec<String>,
+    pub short_title: Option<String>,
+    pub synopsis: Option<String>,
+    pub language: Option<String>,
+    pub dimensions: Option<String>,
+    pub image_url: Option<String>,
+    pub msrp: Option<f64>,
+    pub isbn10: Option<String>,
 }
 
-impl From<IsbnDbBook> for AddBookModel {
-    fn from(item: IsbnDbBook) -> Self {
+impl From<BookRecord> for AddBookModel {
+    fn from(item: BookRecord) -> Self {
         let authors = item.authors.join("; ");
-        let original_date_published = if item.edition == "1st" {
-            Some(item.date_published.clone())
+        let edition = item.edition.unwrap_or_default();
+        let date_published = item.date_published.unwrap_or_default();
+        let original_date_published = if edition == "1st" && !date_published.is_empty() {
+            Some(date_published.clone())
         } else {
             None
         };
+        // The long title is used where there is one, so the short title is only kept when it's
+        // different.
+        let (title, short_title) = match item.title_long {
+            Some(title_long) if title_long != item.title => (title_long, Some(item.title)),
+            Some(title_long) => (title_long, None),
+            None => (item.title, None),
+        };
         AddBookModel {
             authors,
-            publisher: item.publisher,
-            title: item.title_long,
-            edition: item.edition,
-            date_published: item.date_published,
+            publisher: item.publisher.unwrap_or_default(),
+            title,
+            edition,
+            date_published,
             original_date_published,
             price: None,
+            binding: item.binding.unwrap_or_default(),
+            isbn: item.isbn13.or(item.isbn10.clone()),
+            pages: item.pages.unwrap_or(0),
+            owned: true,
+            rating: None,
+            shelf: None,
+            date_read: None,
+            tags: item.subjects,
+            short_title,
+            synopsis: item.synopsis,
+            language: item.language,
+            dimensions: item.dimensions,
+            image_url: item.image_url,
+            msrp: item.msrp,
+            isbn10: item.isbn10,
+        }
+    }
+}
+
+impl From<IsbnDbBook> for AddBookModel {
+    fn from(item: IsbnDbBook) -> Self {
+        AddBookModel::from(BookRecord::from(item))
+    }
+}
+
+impl From<Book> for AddBookModel {
+    fn from(item: Book) -> Self {
+        let authors = item.authors_display();
+        AddBookModel {
+            authors,
+            publisher: item.publisher.name,
+            title: item.title,
+            edition: item.edition,
+            date_published: item.date_published,
+            original_date_published: item.original_date_published,
+            price: item.price,
             binding: item.binding,
-            isbn: item.isbn13,
+            isbn: item.isbn,
             pages: item.pages,
-            owned: true,
+            owned: item.owned,
+            rating: item.rating,
+            shelf: item.shelf,
+            date_read: item.date_read,
+            tags: item.tags,
+            short_title: item.short_title,
+            synopsis: item.synopsis,
+            language: item.language,
+            dimensions: item.dimensions,
+            image_url: item.image_url,
+            msrp: item.msrp,
+            isbn10: item.isbn10,
         }
     }
 }
@@ -56,8 +125,19 @@ impl FromStr for AddBookModel {
         let mut isbn = None;
         let mut pages = None;
         let mut owned = None;
+        let mut rating = None;
+        let mut shelf = None;
+        let mut date_read = None;
+        let mut tags = Vec::new();
+        let mut short_title = None;
+        let mut synopsis = None;
+        let mut language = None;
+        let mut dimensions = None;
+        let mut image_url = None;
+        let mut msrp = None;
+        let mut isbn10 = None;
 
-        for line in s.lines() {
+        for line in s.lines().filter(|line| !line.trim().is_empty()) {
             let mut parts = line.splitn(2, ':');
             let key = parts.next();
             let value = parts.next().unwrap_or("").trim();
@@ -85,7 +165,13 @@ impl FromStr for AddBookModel {
                     }
                 }
                 Some("Binding") => binding = Some(value.to_string()),
-                Some("ISBN") => isbn = Some(value.to_string()),
+                Some("ISBN") => {
+                    isbn = if value.is_empty() {
+                        None
+                    } else {
+                        Some(value.to_string())
+                    }
+                }
                 Some("Pages") => {
                     pages = Some(value.parse().map_err(|_| {
                         Error::ParseError("Could not parse pages field".to_string())
@@ -96,6 +182,38 @@ impl FromStr for AddBookModel {
                         Error::ParseError("Could not parse pages field".to_string())
                     })?)
                 }
+                Some("Rating") => {
+                    rating = if value.is_empty() {
+                        None
+                    } else {
+                        Some(parse_rating(value)?)
+                    }
+                }
+                Some("Shelf") => shelf = non_empty(value),
+                Some("Date Read") => date_read = non_empty(value),
+                Some("Tags") => {
+                    tags = value
+                        .split(';')
+                        .map(str::trim)
+                        .filter(|tag| !tag.is_empty())
+                        .map(str::to_string)
+                        .collect()
+                }
+                Some("ISBN-10") => isbn10 = non_empty(value),
+                Some("Short Title") => short_title = non_empty(value),
+                Some("Synopsis") => synopsis = non_empty(&unescape_line_breaks(value)),
+                Some("Language") => language = non_empty(value),
+                Some("Dimensions") => dimensions = non_empty(value),
+                Some("Image URL") => image_url = non_empty(value),
+                Some("MSRP") => {
+                    msrp = if value.is_empty() {
+                        None
+                    } else {
+                        Some(value.parse().map_err(|_| {
+                            Error::ParseError("Could not parse MSRP field".to_string())
+                        })?)
+                    }
+                }
                 _ => {
                     return Err(Error::ParseError(format!(
                         "Could not parse {} AddBookModel from string",
@@ -116,14 +234,97 @@ impl FromStr for AddBookModel {
             original_date_published,
             price,
             binding: binding.ok_or_else(|| Error::ParseError("Missing binding".to_string()))?,
-            isbn: isbn.ok_or_else(|| Error::ParseError("Missing isbn".to_string()))?,
+            isbn,
             pages: pages.ok_or_else(|| Error::ParseError("Missing pages".to_string()))?,
             owned: owned.ok_or_else(|| Error::ParseError("Missing owned".to_string()))?,
+            rating,
+            shelf,
+            date_read,
+            tags,
+            short_title,
+            synopsis,
+            language,
+            dimensions,
+            image_url,
+            msrp,
+            isbn10,
         })
     }
 }
 
+/// Parse a rating, which must be from 1 to 5.
+pub fn parse_rating(value: &str) -> Result<u8, Error> {
+    match value.trim().parse() {
+        Ok(rating) if (1..=5).contains(&rating) => Ok(rating),
+        _ => Err(Error::ParseError(format!(
+            "The rating should be from 1 to 5, not {value}"
+        ))),
+    }
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+/// The editor works a line at a time, so line breaks in a long field such as the synopsis are
+/// written as `\n`, and a backslash as `\\`.
+fn escape_line_breaks(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+fn unescape_line_breaks(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some(next) => unescaped.push(next),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
 impl AddBookModel {
+    /// A model with empty details, for entering a book by hand.
+    pub fn blank(isbn: Option<String>) -> AddBookModel {
+        AddBookModel {
+            authors: String::new(),
+            publisher: String::new(),
+            title: String::new(),
+            edition: String::new(),
+            date_published: String::new(),
+            original_date_published: None,
+            price: None,
+            binding: String::new(),
+            isbn,
+            pages: 0,
+            owned: true,
+            rating: None,
+            shelf: None,
+            date_read: None,
+            tags: Vec::new(),
+            short_title: None,
+            synopsis: None,
+            language: None,
+            dimensions: None,
+            image_url: None,
+            msrp: None,
+            isbn10: None,
+        }
+    }
+
     pub fn newline(&self) -> &'static str {
         if cfg!(windows) {
             "\r\n"
@@ -138,7 +339,7 @@ impl AddBookModel {
         println!("Edition: {}", self.edition);
         println!("Date Published: {}", self.date_published);
         println!("Binding: {}", self.binding);
-        println!("ISBN: {}", self.isbn);
+        println!("ISBN: {}", self.isbn.as_deref().unwrap_or("N/A"));
         println!("Pages: {}", self.pages);
         println!("Owned: {}", self.owned);
     }
@@ -150,19 +351,44 @@ impl AddBookModel {
             .as_ref()
             .map_or("".to_string(), |date| date.to_string());
         let price = self.price.map_or(String::new(), |price| price.to_string());
+        let isbn = self.isbn.as_deref().unwrap_or("");
+        let isbn10 = self.isbn10.as_deref().unwrap_or("");
+        let rating = self
+            .rating
+            .map_or(String::new(), |rating| rating.to_string());
+        let shelf = self.shelf.as_deref().unwrap_or("");
+        let date_read = self.date_read.as_deref().unwrap_or("");
+        let tags = self.tags.join("; ");
+        let short_title = self.short_title.as_deref().unwrap_or("");
+        let language = self.language.as_deref().unwrap_or("");
+        let dimensions = self.dimensions.as_deref().unwrap_or("");
+        let msrp = self.msrp.map_or(String::new(), |msrp| msrp.to_string());
+        let image_url = self.image_url.as_deref().unwrap_or("");
+        let synopsis = escape_line_breaks(self.synopsis.as_deref().unwrap_or(""));
         format!(
-            "Author(s): {}{newline}Publisher: {}{newline}Title: {}{newline}Edition: {}{newline}Date Published: {}{newline}Original Date Published: {}{newline}Price: {}{newline}Binding: {}{newline}ISBN: {}{newline}Pages: {}{newline}Owned: {}",
+            "Author(s): {}{newline}Publisher: {}{newline}Title: {}{newline}Short Title: {}{newline}Edition: {}{newline}Date Published: {}{newline}Original Date Published: {}{newline}Price: {}{newline}MSRP: {}{newline}Binding: {}{newline}ISBN: {}{newline}ISBN-10: {}{newline}Pages: {}{newline}Language: {}{newline}Dimensions: {}{newline}Owned: {}{newline}Rating: {}{newline}Shelf: {}{newline}Date Read: {}{newline}Tags: {}{newline}Image URL: {}{newline}Synopsis: {}",
             self.authors,
             self.publisher,
             self.title,
+            short_title,
             self.edition,
             self.date_published,
             original_date_published,
             price,
+            msrp,
             self.binding,
-            self.isbn,
+            isbn,
+            isbn10,
             self.pages,
+            language,
+            dimensions,
             self.owned,
+            rating,
+            shelf,
+            date_read,
+            tags,
+            image_url,
+            synopsis,
             newline = newline
         )
     }
@@ -177,21 +403,21 @@ mod test {
     #[test]
     fn from_should_convert_the_isbn_record_to_an_add_book_model() -> Result<()> {
         let isbn_book = IsbnDbBook {
-            publisher: "Carlton Publishing Group".to_string(),
+            publisher: Some("Carlton Publishing Group".to_string()),
             authors: vec!["Reeve, Simon".to_string()],
-            language: "en".to_string(),
-            image_url: "https://images.isbndb.com/covers/04/85/9780233050485.jpg".to_string(),
-            title_long: "The New Jackals: Osama Bin Laden and the Future of Terrorism".to_string(),
-            edition: "2nd".to_string(),
-            dimensions: "Height: 7.71652 Inches.to_string(), Length: 5.07873 Inches, Weight: 0.661386786 Pounds, Width: 0.7874 Inches".to_string(),
-            pages: 352,
-            date_published: "2001".to_string(),
+            language: Some("en".to_string()),
+            image_url: Some("https://images.isbndb.com/covers/04/85/9780233050485.jpg".to_string()),
+            title_long: Some("The New Jackals: Osama Bin Laden and the Future of Terrorism".to_string()),
+            edition: Some("2nd".to_string()),
+            dimensions: Some("Height: 7.71652 Inches.to_string(), Length: 5.07873 Inches, Weight: 0.661386786 Pounds, Width: 0.7874 Inches".to_string()),
+            pages: Some(352),
+            date_published: Some("2001".to_string()),
             title: "The New Jackals".to_string(),
-            isbn13: "9780233050485".to_string(),
-            msrp: 17.75,
-            binding: "Paperback".to_string(),
-            isbn: "0233050485".to_string(),
-            isbn10: "0233050485".to_string(),
+            isbn13: Some("9780233050485".to_string()),
+            msrp: Some(17.75),
+            binding: Some("Paperback".to_string()),
+            isbn: Some("0233050485".to_string()),
+            isbn10: Some("0233050485".to_string()),
             subjects: None,
             synopsis: None,
         };
@@ -207,10 +433,17 @@ mod test {
             model.title,
             "The New Jackals: Osama Bin Laden and the Future of Terrorism"
         );
-        assert_eq!(model.isbn, "9780233050485");
+        assert_eq!(model.isbn, Some("9780233050485".to_string()));
         assert_eq!(model.binding, "Paperback");
         assert_eq!(model.authors, "Reeve, Simon");
         assert!(model.owned);
+        assert_eq!(model.short_title, Some("The New Jackals".to_string()));
+        assert_eq!(model.language, Some("en".to_string()));
+        assert_eq!(model.msrp, Some(17.75));
+        assert_eq!(
+            model.image_url,
+            Some("https://images.isbndb.com/covers/04/85/9780233050485.jpg".to_string())
+        );
         Ok(())
     }
 
@@ -218,26 +451,26 @@ mod test {
     fn from_should_convert_the_isbn_record_with_multiple_authors_to_an_add_book_model() -> Result<()>
     {
         let isbn_book = IsbnDbBook {
-            publisher: "Crown".to_string(),
+            publisher: Some("Crown".to_string()),
             authors: vec![
                 "Dwyer, Jim".to_string(),
                 "Murphy, Deidre".to_string(),
                 "Tyre, Peg".to_string(),
                 "Kocieniewski, David".to_string(),
             ],
-            language: "en".to_string(),
-            image_url: "https://images.isbndb.com/covers/76/75/9780517597675.jpg".to_string(),
-            title_long: "Two Seconds Under the World:Terror Comes to America-The Conspiracy Behind the World Trade Center Bombing".to_string(),
-            edition: "1st".to_string(),
-            dimensions: "Height: 9.5 Inches, Length: 6.25 Inches, Weight: 1.4 Pounds, Width: 1 Inches".to_string(),
-            pages: 322,
-            date_published: "1997".to_string(),
+            language: Some("en".to_string()),
+            image_url: Some("https://images.isbndb.com/covers/76/75/9780517597675.jpg".to_string()),
+            title_long: Some("Two Seconds Under the World:Terror Comes to America-The Conspiracy Behind the World Trade Center Bombing".to_string()),
+            edition: Some("1st".to_string()),
+            dimensions: Some("Height: 9.5 Inches, Length: 6.25 Inches, Weight: 1.4 Pounds, Width: 1 Inches".to_string()),
+            pages: Some(322),
+            date_published: Some("1997".to_string()),
             title: "Two Seconds Under the World:Terror Comes to America-The Conspiracy Behind the World Trade Center Bombing".to_string(),
-            isbn13: "9780517597675".to_string(),
-            msrp: 24_f32,
-            binding: "Hardcover".to_string(),
-            isbn: "0517597675".to_string(),
-            isbn10: "0517597675".to_string(),
+            isbn13: Some("9780517597675".to_string()),
+            msrp: Some(24.0),
+            binding: Some("Hardcover".to_string()),
+            isbn: Some("0517597675".to_string()),
+            isbn10: Some("0517597675".to_string()),
             subjects:
                 Some(vec![
                     "World Trade Center Bombing, New York, N.Y., 1993".to_string(),
@@ -259,13 +492,29 @@ mod test {
             model.title,
             "Two Seconds Under the World:Terror Comes to America-The Conspiracy Behind the World Trade Center Bombing"
         );
-        assert_eq!(model.isbn, "9780517597675");
+        assert_eq!(model.isbn, Some("9780517597675".to_string()));
         assert_eq!(model.binding, "Hardcover");
         assert_eq!(
             model.authors,
             "Dwyer, Jim; Murphy, Deidre; Tyre, Peg; Kocieniewski, David"
         );
         assert!(model.owned);
+        assert_eq!(model.tags.len(), 5);
+        assert_eq!(model.tags[1], "Terrorism");
+        // The long title is the same as the title, so there's no short title to keep.
+        assert_eq!(model.short_title, None);
+        assert!(model
+            .synopsis
+            .as_deref()
+            .unwrap()
+            .starts_with("Text And Accompanying Photographs"));
+        assert_eq!(
+            model.dimensions,
+            Some(
+                "Height: 9.5 Inches, Length: 6.25 Inches, Weight: 1.4 Pounds, Width: 1 Inches"
+                    .to_string()
+            )
+        );
         // If the book is first edition, the original date should be set automatically.
         assert_eq!(model.original_date_published, Some("1997".to_string()));
         Ok(())
@@ -282,9 +531,20 @@ mod test {
             original_date_published: Some("1999".to_string()),
             price: Some(20.0),
             binding: "Paperback".to_string(),
-            isbn: "9780233050485".to_string(),
+            isbn: Some("9780233050485".to_string()),
             pages: 352,
             owned: true,
+            rating: Some(4),
+            shelf: Some("read".to_string()),
+            date_read: None,
+            tags: vec!["Terrorism".to_string(), "History".to_string()],
+            short_title: Some("The New Jackals".to_string()),
+            synopsis: Some("A study of the new terrorism.".to_string()),
+            language: Some("en".to_string()),
+            dimensions: None,
+            image_url: None,
+            msrp: Some(17.75),
+            isbn10: Some("0233050485".to_string()),
         };
         let edit = model.to_editor();
         let newline = model.newline();
@@ -292,20 +552,66 @@ mod test {
             "Author(s): Reeve, Simon{nl}\
             Publisher: Carlton Publishing Group{nl}\
             Title: The New Jackals: Osama Bin Laden and the Future of Terrorism{nl}\
+            Short Title: The New Jackals{nl}\
             Edition: 2nd{nl}\
             Date Published: 2001{nl}\
             Original Date Published: 1999{nl}\
             Price: 20{nl}\
+            MSRP: 17.75{nl}\
             Binding: Paperback{nl}\
             ISBN: 9780233050485{nl}\
+            ISBN-10: 0233050485{nl}\
             Pages: 352{nl}\
-            Owned: true",
+            Language: en{nl}\
+            Dimensions: {nl}\
+            Owned: true{nl}\
+            Rating: 4{nl}\
+            Shelf: read{nl}\
+            Date Read: {nl}\
+            Tags: Terrorism; History{nl}\
+            Image URL: {nl}\
+            Synopsis: A study of the new terrorism.",
             nl = newline
         );
         assert_eq!(edit, expected);
         Ok(())
     }
 
+    #[test]
+    fn from_should_keep_the_synopsis_as_given_by_the_provider() {
+        let record = BookRecord {
+            title: "The New Jackals".to_string(),
+            synopsis: Some("The first paragraph.\n\nThe second.".to_string()),
+            ..Default::default()
+        };
+
+        let model = AddBookModel::from(record);
+        assert_eq!(
+            model.synopsis,
+            Some("The first paragraph.\n\nThe second.".to_string())
+        );
+    }
+
+    #[test]
+    fn to_editor_should_keep_a_synopsis_with_line_breaks_on_one_line() -> Result<()> {
+        let model = AddBookModel {
+            title: "The New Jackals".to_string(),
+            authors: "Reeve, Simon".to_string(),
+            publisher: "Carlton Publishing Group".to_string(),
+            synopsis: Some("The first paragraph.\n\nThe second, with a \\ in it.".to_string()),
+            ..AddBookModel::blank(None)
+        };
+
+        let edit = model.to_editor();
+        assert!(
+            edit.ends_with("Synopsis: The first paragraph.\\n\\nThe second, with a \\\\ in it.")
+        );
+
+        let parsed: AddBookModel = edit.parse()?;
+        assert_eq!(parsed.synopsis, model.synopsis);
+        Ok(())
+    }
+
     #[test]
     fn parse_should_convert_an_edited_book_string_to_an_add_book_model() {
         let edited = "Author(s): Reeve, Simon\n\
@@ -318,7 +624,16 @@ mod test {
          Binding: Paperback\n\
          ISBN: 9780233050485\n\
          Pages: 352\n\
-         Owned: true";
+         Owned: true\n\
+         Rating: 5\n\
+         Shelf: read\n\
+         Date Read: 2021-03-14\n\
+         Tags: Terrorism; History\n\
+         Short Title: The New Jackals\n\
+         MSRP: 17.75\n\
+         Language: en\n\
+         Image URL: https://images.isbndb.com/covers/04/85/9780233050485.jpg\n\
+         Synopsis: Osama Bin Laden: the future of terrorism.";
 
         let model: AddBookModel = edited.parse().unwrap();
 
@@ -330,10 +645,27 @@ mod test {
         );
         assert_eq!(model.edition, "2nd");
         assert_eq!(model.date_published, "2001");
+        assert_eq!(model.rating, Some(5));
+        assert_eq!(model.shelf, Some("read".to_string()));
+        assert_eq!(model.date_read, Some("2021-03-14".to_string()));
+        assert_eq!(model.tags, vec!["Terrorism", "History"]);
+        assert_eq!(model.short_title, Some("The New Jackals".to_string()));
+        assert_eq!(model.msrp, Some(17.75));
+        assert_eq!(model.language, Some("en".to_string()));
+        assert_eq!(model.dimensions, None);
+        assert_eq!(
+            model.image_url,
+            Some("https://images.isbndb.com/covers/04/85/9780233050485.jpg".to_string())
+        );
+        // Only the first colon separates the field name from its value.
+        assert_eq!(
+            model.synopsis,
+            Some("Osama Bin Laden: the future of terrorism.".to_string())
+        );
         assert_eq!(model.original_date_published, Some("1999".to_string()));
         assert_eq!(model.price, Some(20.0));
         assert_eq!(model.binding, "Paperback");
-        assert_eq!(model.isbn, "9780233050485");
+        assert_eq!(model.isbn, Some("9780233050485".to_string()));
         assert_eq!(model.pages, 352);
         assert!(model.owned);
     }
@@ -364,7 +696,7 @@ mod test {
         assert_eq!(model.original_date_published, None);
         assert_eq!(model.price, None);
         assert_eq!(model.binding, "Paperback");
-        assert_eq!(model.isbn, "9780233050485");
+        assert_eq!(model.isbn, Some("9780233050485".to_string()));
         assert_eq!(model.pages, 352);
         assert!(model.owned);
     }
@@ -397,8 +729,28 @@ mod test {
         assert_eq!(model.original_date_published, None);
         assert_eq!(model.price, None);
         assert_eq!(model.binding, "Paperback");
-        assert_eq!(model.isbn, "9780233050485");
+        assert_eq!(model.isbn, Some("9780233050485".to_string()));
         assert_eq!(model.pages, 352);
         assert!(model.owned);
     }
+
+    #[test]
+    fn parse_should_convert_an_edited_blank_model_without_an_isbn_to_an_add_book_model() {
+        let mut model = AddBookModel::blank(None);
+        model.authors = "Homer".to_string();
+        model.publisher = "Penguin Classics".to_string();
+        model.title = "The Odyssey".to_string();
+        model.date_published = "1946".to_string();
+        model.binding = "Paperback".to_string();
+        let edited = format!("{}\n\n", model.to_editor());
+
+        let model: AddBookModel = edited.parse().unwrap();
+
+        assert_eq!(model.authors, "Homer");
+        assert_eq!(model.title, "The Odyssey");
+        assert_eq!(model.edition, "");
+        assert_eq!(model.isbn, None);
+        assert_eq!(model.pages, 0);
+        assert!(model.owned);
+    }
 }
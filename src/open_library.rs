@@ -0,0 +1,412 @@
+use crate::error::{Error, Result};
+use crate::metadata::{surname_first, BookRecord, MetadataProvider, SearchQuery};
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde_derive::Deserialize;
+
+const COVERS_URL: &str = "https://covers.openlibrary.org";
+const SEARCH_LIMIT: u32 = 20;
+
+#[derive(Debug, Deserialize)]
+struct Key {
+    key: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Edition {
+    title: String,
+    subtitle: Option<String>,
+    #[serde(default)]
+    publishers: Vec<String>,
+    publish_date: Option<String>,
+    number_of_pages: Option<u32>,
+    #[serde(default)]
+    authors: Vec<Key>,
+    #[serde(default)]
+    works: Vec<Key>,
+    physical_format: Option<String>,
+    edition_name: Option<String>,
+    #[serde(default)]
+    isbn_13: Vec<String>,
+    #[serde(default)]
+    isbn_10: Vec<String>,
+    #[serde(default)]
+    languages: Vec<Key>,
+    #[serde(default)]
+    covers: Vec<i64>,
+    physical_dimensions: Option<String>,
+    #[serde(default)]
+    subjects: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Author {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct WorkAuthor {
+    author: Key,
+}
+
+/// Open Library gives descriptions either as plain strings or as typed text objects.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Description {
+    Text(String),
+    Typed { value: String },
+}
+
+#[derive(Debug, Deserialize)]
+struct Work {
+    #[serde(default)]
+    authors: Vec<WorkAuthor>,
+    description: Option<Description>,
+    #[serde(default)]
+    subjects: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResponse {
+    docs: Vec<SearchDoc>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchDoc {
+    title: String,
+    #[serde(default)]
+    author_name: Vec<String>,
+    #[serde(default)]
+    publisher: Vec<String>,
+    first_publish_year: Option<u32>,
+    number_of_pages_median: Option<u32>,
+    #[serde(default)]
+    isbn: Vec<String>,
+    #[serde(default)]
+    subject: Vec<String>,
+}
+
+impl From<SearchDoc> for BookRecord {
+    fn from(item: SearchDoc) -> Self {
+        BookRecord {
+            title: item.title,
+            authors: item.author_name.iter().map(|a| surname_first(a)).collect(),
+            publisher: item.publisher.into_iter().next(),
+            date_published: item.first_publish_year.map(|year| year.to_string()),
+            pages: item.number_of_pages_median,
+            isbn13: item.isbn.iter().find(|isbn| isbn.len() == 13).cloned(),
+            isbn10: item.isbn.iter().find(|isbn| isbn.len() == 10).cloned(),
+            subjects: item.subject,
+            ..Default::default()
+        }
+    }
+}
+
+/// Looks up books using the Open Library API, which doesn't require a key.
+///
+/// An ISBN identifies an edition. The authors are separate records that need to be fetched by
+/// their keys, and the description and subjects are usually held on the work the edition belongs
+/// to rather than the edition itself.
+pub struct OpenLibraryRepository {
+    pub base_url: String,
+}
+
+impl OpenLibraryRepository {
+    pub fn new(base_url: &str) -> OpenLibraryRepository {
+        OpenLibraryRepository {
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Request a JSON document, with `None` indicating that it doesn't exist.
+    async fn get_json<T: DeserializeOwned>(&self, path: &str) -> Result<Option<T>> {
+        let url = format!("{}{}", self.base_url, path);
+        let resp = reqwest::Client::new()
+            .get(&url)
+            .header("accept", "application/json")
+            .send()
+            .await?;
+        if resp.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let body = resp.error_for_status()?.text().await?;
+        Ok(Some(serde_json::from_str(&body)?))
+    }
+
+    async fn get_author_name(&self, key: &str) -> Result<Option<String>> {
+        let author: Option<Author> = self.get_json(&format!("{key}.json")).await?;
+        Ok(author.map(|a| surname_first(&a.name)))
+    }
+}
+
+#[async_trait]
+impl MetadataProvider for OpenLibraryRepository {
+    async fn lookup_by_isbn(&self, isbn: &str) -> Result<BookRecord> {
+        let edition: Edition = self
+            .get_json(&format!("/isbn/{isbn}.json"))
+            .await?
+            .ok_or_else(|| Error::RecordNotFound {
+                provider: "Open Library".to_string(),
+                isbn: isbn.to_string(),
+            })?;
+        let work: Option<Work> = match edition.works.first() {
+            Some(work) => self.get_json(&format!("{}.json", work.key)).await?,
+            None => None,
+        };
+
+        let mut author_keys: Vec<&str> = edition.authors.iter().map(|a| a.key.as_str()).collect();
+        if author_keys.is_empty() {
+            if let Some(work) = &work {
+                author_keys = work.authors.iter().map(|a| a.author.key.as_str()).collect();
+            }
+        }
+        let mut authors = Vec::new();
+        for key in author_keys {
+            if let Some(name) = self.get_author_name(key).await? {
+                authors.push(name);
+            }
+        }
+
+        let (synopsis, mut subjects) = match work {
+            Some(work) => (
+                work.description.map(|d| match d {
+                    Description::Text(text) => text,
+                    Description::Typed { value } => value,
+                }),
+                work.subjects,
+            ),
+            None => (None, Vec::new()),
+        };
+        if subjects.is_empty() {
+            subjects = edition.subjects;
+        }
+
+        let title_long = edition
+            .subtitle
+            .as_ref()
+            .map(|subtitle| format!("{}: {}", edition.title, subtitle));
+        Ok(BookRecord {
+            title: edition.title,
+            title_long,
+            authors,
+            publisher: edition.publishers.into_iter().next(),
+            edition: edition
+                .edition_name
+                .map(|e| e.trim_end_matches(" ed.").trim().to_string()),
+            date_published: edition.publish_date,
+            binding: edition.physical_format,
+            pages: edition.number_of_pages,
+            isbn13: edition.isbn_13.into_iter().next(),
+            isbn10: edition.isbn_10.into_iter().next(),
+            language: edition
+                .languages
+                .first()
+                .map(|l| l.key.trim_start_matches("/languages/").to_string()),
+            subjects,
+            synopsis,
+            image_url: edition
+                .covers
+                .first()
+                .map(|id| format!("{COVERS_URL}/b/id/{id}-L.jpg")),
+            dimensions: edition.physical_dimensions,
+            msrp: None,
+        })
+    }
+
+    async fn search(&self, query: &SearchQuery) -> Result<Vec<BookRecord>> {
+        let mut params = vec![("limit", SEARCH_LIMIT.to_string())];
+        if let Some(title) = &query.title {
+            params.push(("title", title.clone()));
+        }
+        if let Some(author) = &query.author {
+            params.push(("author", author.clone()));
+        }
+        let url = format!("{}/search.json", self.base_url);
+        let resp = reqwest::Client::new()
+            .get(&url)
+            .query(&params)
+            .header("accept", "application/json")
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        let response: SearchResponse = serde_json::from_str(&resp)?;
+        Ok(response.docs.into_iter().map(BookRecord::from).collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::OpenLibraryRepository;
+    use crate::error::Error;
+    use crate::metadata::{MetadataProvider, SearchQuery};
+    use crate::models::AddBookModel;
+    use color_eyre::Result;
+    use httpmock::prelude::*;
+
+    fn read_resource(name: &str) -> Result<String> {
+        Ok(std::fs::read_to_string(
+            std::path::Path::new("resources").join(name),
+        )?)
+    }
+
+    #[tokio::test]
+    async fn lookup_by_isbn_should_return_book_record_with_authors_and_work_details() -> Result<()>
+    {
+        let isbn = "9780517597675";
+        let server = MockServer::start();
+        let edition_mock = server.mock(|when, then| {
+            when.method(GET).path(format!("/isbn/{isbn}.json"));
+            then.status(200)
+                .body(read_resource("open_library_edition_response_body.json").unwrap());
+        });
+        let work_mock = server.mock(|when, then| {
+            when.method(GET).path("/works/OL2653307W.json");
+            then.status(200)
+                .body(read_resource("open_library_work_response_body.json").unwrap());
+        });
+        let dwyer_mock = server.mock(|when, then| {
+            when.method(GET).path("/authors/OL225463A.json");
+            then.status(200)
+                .body(read_resource("open_library_author_dwyer_response_body.json").unwrap());
+        });
+        let murphy_mock = server.mock(|when, then| {
+            when.method(GET).path("/authors/OL2646025A.json");
+            then.status(200)
+                .body(read_resource("open_library_author_murphy_response_body.json").unwrap());
+        });
+
+        let repository = OpenLibraryRepository::new(&server.base_url());
+        let record = repository.lookup_by_isbn(isbn).await?;
+
+        assert_eq!(record.title, "Two Seconds Under the World");
+        assert_eq!(
+            record.title_long,
+            Some("Two Seconds Under the World: Terror Comes to America-The Conspiracy Behind the World Trade Center Bombing".to_string())
+        );
+        assert_eq!(record.authors, vec!["Dwyer, Jim", "Murphy, Deidre"]);
+        assert_eq!(record.publisher, Some("Crown".to_string()));
+        assert_eq!(record.edition, Some("1st".to_string()));
+        assert_eq!(record.date_published, Some("1994".to_string()));
+        assert_eq!(record.binding, Some("Hardcover".to_string()));
+        assert_eq!(record.pages, Some(322));
+        assert_eq!(record.isbn13, Some(isbn.to_string()));
+        assert_eq!(record.isbn10, Some("0517597675".to_string()));
+        assert_eq!(record.language, Some("eng".to_string()));
+        assert_eq!(
+            record.subjects,
+            vec![
+                "World Trade Center Bombing, New York, N.Y., 1993",
+                "Terrorism"
+            ]
+        );
+        assert_eq!(record.synopsis, Some("Text and accompanying photographs present the story of the bombing of the World Trade Center by Islamic fundamentalist terrorists.".to_string()));
+        assert_eq!(
+            record.image_url,
+            Some("https://covers.openlibrary.org/b/id/8231856-L.jpg".to_string())
+        );
+        assert_eq!(
+            record.dimensions,
+            Some("24 x 16 x 3 centimeters".to_string())
+        );
+
+        let model = AddBookModel::from(record);
+        assert_eq!(model.authors, "Dwyer, Jim; Murphy, Deidre");
+        assert_eq!(model.original_date_published, Some("1994".to_string()));
+
+        edition_mock.assert();
+        work_mock.assert();
+        dwyer_mock.assert();
+        murphy_mock.assert();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn lookup_by_isbn_when_edition_has_no_authors_should_use_the_work_authors() -> Result<()>
+    {
+        let isbn = "9780233050485";
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(GET).path(format!("/isbn/{isbn}.json"));
+            then.status(200).body(
+                read_resource("open_library_edition_without_authors_response_body.json").unwrap(),
+            );
+        });
+        server.mock(|when, then| {
+            when.method(GET).path("/works/OL4310121W.json");
+            then.status(200).body(
+                read_resource("open_library_work_without_authors_on_edition_response_body.json")
+                    .unwrap(),
+            );
+        });
+        server.mock(|when, then| {
+            when.method(GET).path("/authors/OL1396370A.json");
+            then.status(200)
+                .body(read_resource("open_library_author_reeve_response_body.json").unwrap());
+        });
+
+        let repository = OpenLibraryRepository::new(&server.base_url());
+        let record = repository.lookup_by_isbn(isbn).await?;
+
+        assert_eq!(record.title, "The New Jackals");
+        assert_eq!(record.title_long, None);
+        assert_eq!(record.authors, vec!["Reeve, Simon"]);
+        assert_eq!(record.publisher, Some("Carlton".to_string()));
+        assert_eq!(record.edition, None);
+        assert_eq!(record.pages, None);
+        assert_eq!(
+            record.synopsis,
+            Some("Ramzi Yousef, Osama bin Laden and the future of terrorism.".to_string())
+        );
+        assert!(record.subjects.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn lookup_by_isbn_when_book_is_unknown_should_return_not_found_error() -> Result<()> {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(GET).path("/isbn/9780000000002.json");
+            then.status(404).body("{\"error\": \"notfound\"}");
+        });
+
+        let repository = OpenLibraryRepository::new(&server.base_url());
+        let result = repository.lookup_by_isbn("9780000000002").await;
+
+        assert!(matches!(result, Err(Error::RecordNotFound { .. })));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_should_return_book_records() -> Result<()> {
+        let server = MockServer::start();
+        let search_mock = server.mock(|when, then| {
+            when.method(GET)
+                .path("/search.json")
+                .query_param("title", "two seconds")
+                .query_param("author", "dwyer");
+            then.status(200)
+                .body(read_resource("open_library_search_response_body.json").unwrap());
+        });
+
+        let repository = OpenLibraryRepository::new(&server.base_url());
+        let records = repository
+            .search(&SearchQuery {
+                title: Some("two seconds".to_string()),
+                author: Some("dwyer".to_string()),
+            })
+            .await?;
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].title, "Two Seconds Under the World");
+        assert_eq!(records[0].authors[0], "Dwyer, Jim");
+        assert_eq!(records[0].authors[3], "Kocieniewski, David");
+        assert_eq!(records[0].isbn13, Some("9780517597675".to_string()));
+        assert_eq!(records[0].isbn10, Some("0517597675".to_string()));
+        assert_eq!(records[0].date_published, Some("1994".to_string()));
+        search_mock.assert();
+        Ok(())
+    }
+}
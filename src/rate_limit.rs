@@ -0,0 +1,112 @@
+use rand::Rng;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+/// Spaces out requests so that no more than a given number are sent each second.
+pub struct Throttle {
+    interval: Duration,
+    next: Mutex<Instant>,
+}
+
+impl Throttle {
+    /// A rate of zero or less means requests aren't throttled.
+    pub fn new(requests_per_second: f64) -> Throttle {
+        let interval = if requests_per_second > 0.0 {
+            Duration::from_secs_f64(1.0 / requests_per_second)
+        } else {
+            Duration::ZERO
+        };
+        Throttle {
+            interval,
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    pub fn unlimited() -> Throttle {
+        Throttle::new(0.0)
+    }
+
+    /// Wait until the next request is allowed to be sent.
+    pub async fn wait(&self) {
+        let mut next = self.next.lock().await;
+        let now = Instant::now();
+        if *next > now {
+            tokio::time::sleep_until(*next).await;
+        }
+        *next = (*next).max(now) + self.interval;
+    }
+}
+
+/// How often, and how patiently, a failed request is retried.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    /// The total number of attempts, including the first
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 4,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The delay before the retry that follows the given attempt, counting from 1.
+    ///
+    /// The delay doubles with each attempt, up to the maximum. Half of it is random, so that
+    /// clients that failed together don't all retry at the same moment.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exponential = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_delay);
+        let half = exponential / 2;
+        half + half.mul_f64(rand::thread_rng().gen::<f64>())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{RetryPolicy, Throttle};
+    use std::time::Duration;
+    use tokio::time::Instant;
+
+    #[test]
+    fn delay_should_grow_exponentially_up_to_the_maximum() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(1000),
+        };
+        for (attempt, expected) in [(1, 100), (2, 200), (3, 400), (4, 800), (5, 1000), (9, 1000)] {
+            let delay = policy.delay(attempt);
+            let expected = Duration::from_millis(expected);
+            assert!(delay >= expected / 2 && delay <= expected, "{delay:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn wait_should_space_out_requests() {
+        let throttle = Throttle::new(20.0);
+        let start = Instant::now();
+        for _ in 0..3 {
+            throttle.wait().await;
+        }
+        // The first request goes straight away, and each of the others waits 50ms.
+        assert!(start.elapsed() >= Duration::from_millis(100));
+
+        let throttle = Throttle::unlimited();
+        let start = Instant::now();
+        for _ in 0..3 {
+            throttle.wait().await;
+        }
+        assert!(start.elapsed() < Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,421 @@
+use crate::books::Book;
+use crate::covers::COVERS_DIR;
+use crate::error::Result;
+use askama::Template;
+use serde_derive::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+const STYLE_SHEET: &str = include_str!("../templates/site/style.css");
+const SCRIPT: &str = include_str!("../templates/site/site.js");
+
+/// Leading articles that are ignored when sorting by title.
+const SORT_ARTICLES: [&str; 3] = ["the ", "an ", "a "];
+
+/// A link to an author's or publisher's page.
+#[derive(Clone, Debug)]
+pub struct Link {
+    pub id: u32,
+    pub name: String,
+}
+
+/// The parts of a book shown on the site, prepared for the templates.
+#[derive(Debug)]
+pub struct BookView {
+    pub id: u32,
+    pub title: String,
+    pub authors: Vec<Link>,
+    pub publisher: Link,
+    pub year: String,
+    pub sort_title: String,
+    pub sort_author: String,
+    /// The rows shown on the book's page after the authors and publisher
+    pub details: Vec<(&'static str, String)>,
+    /// The address of the cover image, relative to the root of the site
+    pub cover: Option<String>,
+}
+
+impl From<&Book> for BookView {
+    fn from(book: &Book) -> Self {
+        let mut details = vec![
+            ("Edition", book.edition.clone()),
+            ("Date Published", book.date_published.clone()),
+            (
+                "Original Date Published",
+                book.original_date_published.clone().unwrap_or_default(),
+            ),
+            ("Binding", book.binding.clone()),
+            (
+                "Pages",
+                match book.pages {
+                    0 => String::new(),
+                    pages => pages.to_string(),
+                },
+            ),
+            (
+                "ISBN",
+                book.isbn
+                    .as_ref()
+                    .map(|_| book.isbn_display())
+                    .unwrap_or_default(),
+            ),
+            ("ISBN-10", book.isbn10_display().unwrap_or_default()),
+            ("Owned", if book.owned { "Yes" } else { "No" }.to_string()),
+            (
+                "Rating",
+                book.rating.map(|r| format!("{r}/5")).unwrap_or_default(),
+            ),
+            ("Shelf", book.shelf.clone().unwrap_or_default()),
+            ("Date Read", book.date_read.clone().unwrap_or_default()),
+            ("Tags", book.tags.join("; ")),
+        ];
+        details.retain(|(_, value)| !value.is_empty());
+
+        let lowercase_title = book.title.to_lowercase();
+        let sort_title = SORT_ARTICLES
+            .iter()
+            .find_map(|article| lowercase_title.strip_prefix(article))
+            .unwrap_or(&lowercase_title)
+            .to_string();
+        BookView {
+            id: book.id,
+            title: book.title.clone(),
+            authors: book
+                .authors
+                .iter()
+                .map(|author| Link {
+                    id: author.id,
+                    name: author.to_string(),
+                })
+                .collect(),
+            publisher: Link {
+                id: book.publisher.id,
+                name: book.publisher.name.clone(),
+            },
+            year: book.year_published().unwrap_or_default(),
+            sort_title,
+            sort_author: book
+                .authors
+                .first()
+                .map(|author| author.to_string().to_lowercase())
+                .unwrap_or_default(),
+            details,
+            cover: cover_address(book),
+        }
+    }
+}
+
+impl BookView {
+    fn authors_display(&self) -> String {
+        self.authors
+            .iter()
+            .map(|author| author.name.as_str())
+            .collect::<Vec<&str>>()
+            .join("; ")
+    }
+}
+
+#[derive(Template)]
+#[template(path = "site/index.html")]
+struct IndexPage<'a> {
+    root: &'a str,
+    books: &'a [&'a BookView],
+}
+
+#[derive(Template)]
+#[template(path = "site/book.html")]
+struct BookPage<'a> {
+    root: &'a str,
+    book: &'a BookView,
+}
+
+/// An author's or publisher's page, listing their books.
+#[derive(Template)]
+#[template(path = "site/listing.html")]
+struct ListingPage<'a> {
+    root: &'a str,
+    heading: &'a str,
+    books: &'a [&'a BookView],
+}
+
+/// The list of every author or publisher, with the number of books for each.
+#[derive(Template)]
+#[template(path = "site/links.html")]
+struct LinksPage<'a> {
+    root: &'a str,
+    heading: &'a str,
+    directory: &'a str,
+    links: Vec<(Link, usize)>,
+}
+
+/// An entry in the search index used by the index page.
+#[derive(Serialize)]
+struct SearchEntry<'a> {
+    url: String,
+    title: &'a str,
+    authors: String,
+    /// The lowercase text that search terms are matched against
+    text: String,
+}
+
+/// Render the collection as a static website in the output directory, returning the number of
+/// pages written.
+///
+/// The index lists every book, with one page for each book, author and publisher. The search
+/// index is written as a script rather than JSON, so the pages work when opened straight from
+/// the file system. Downloaded covers are copied into the site rather than linking to the
+/// provider's images.
+pub fn generate(books: &[Book], output_dir: &Path) -> Result<usize> {
+    let mut views: Vec<BookView> = books.iter().map(BookView::from).collect();
+    views.sort_by(|a, b| a.sort_title.cmp(&b.sort_title).then(a.id.cmp(&b.id)));
+    let views: Vec<&BookView> = views.iter().collect();
+
+    let mut pages = Vec::new();
+    pages.push((
+        "index.html".to_string(),
+        IndexPage {
+            root: "",
+            books: &views,
+        }
+        .render()?,
+    ));
+    for book in views.iter() {
+        pages.push((
+            format!("books/{}.html", book.id),
+            BookPage { root: "../", book }.render()?,
+        ));
+    }
+
+    let mut authors: BTreeMap<u32, (String, Vec<&BookView>)> = BTreeMap::new();
+    let mut publishers: BTreeMap<u32, (String, Vec<&BookView>)> = BTreeMap::new();
+    for book in views.iter() {
+        for author in book.authors.iter() {
+            authors
+                .entry(author.id)
+                .or_insert_with(|| (author.name.clone(), Vec::new()))
+                .1
+                .push(book);
+        }
+        publishers
+            .entry(book.publisher.id)
+            .or_insert_with(|| (book.publisher.name.clone(), Vec::new()))
+            .1
+            .push(book);
+    }
+    for (directory, heading, groups) in [
+        ("authors", "Authors", &authors),
+        ("publishers", "Publishers", &publishers),
+    ] {
+        for (id, (name, books)) in groups.iter() {
+            pages.push((
+                format!("{directory}/{id}.html"),
+                ListingPage {
+                    root: "../",
+                    heading: name,
+                    books,
+                }
+                .render()?,
+            ));
+        }
+        let mut links: Vec<(Link, usize)> = groups
+            .iter()
+            .map(|(id, (name, books))| {
+                let link = Link {
+                    id: *id,
+                    name: name.clone(),
+                };
+                (link, books.len())
+            })
+            .collect();
+        links.sort_by_key(|(link, _)| link.name.to_lowercase());
+        pages.push((
+            format!("{directory}.html"),
+            LinksPage {
+                root: "",
+                heading,
+                directory,
+                links,
+            }
+            .render()?,
+        ));
+    }
+
+    for directory in ["books", "authors", "publishers", COVERS_DIR] {
+        std::fs::create_dir_all(output_dir.join(directory))?;
+    }
+    for (path, page) in pages.iter() {
+        std::fs::write(output_dir.join(path), page)?;
+    }
+    for book in books.iter() {
+        if let (Some(cover), Some(address)) = (&book.cover, cover_address(book)) {
+            std::fs::copy(&cover.path, output_dir.join(address))?;
+        }
+    }
+    std::fs::write(output_dir.join("style.css"), STYLE_SHEET)?;
+    std::fs::write(output_dir.join("site.js"), SCRIPT)?;
+    std::fs::write(output_dir.join("search-index.js"), search_index(&views)?)?;
+    Ok(pages.len())
+}
+
+/// Where the book's cover is copied to in the site, if it has been downloaded and is still there.
+fn cover_address(book: &Book) -> Option<String> {
+    book.cover
+        .as_ref()
+        .filter(|cover| cover.path.is_file())
+        .and_then(|cover| cover.file_name())
+        .map(|name| format!("{COVERS_DIR}/{name}"))
+}
+
+fn search_index(books: &[&BookView]) -> Result<String> {
+    let entries: Vec<SearchEntry> = books
+        .iter()
+        .map(|book| {
+            let authors = book.authors_display();
+            let isbn = book
+                .details
+                .iter()
+                .filter(|(label, _)| label.starts_with("ISBN"))
+                .map(|(_, value)| value.replace('-', ""))
+                .collect::<Vec<String>>()
+                .join(" ");
+            let text = format!(
+                "{} {} {} {} {}",
+                book.title, authors, book.publisher.name, book.year, isbn
+            );
+            SearchEntry {
+                url: format!("books/{}.html", book.id),
+                title: &book.title,
+                authors,
+                text: text.to_lowercase(),
+            }
+        })
+        .collect();
+    Ok(format!(
+        "window.SEARCH_INDEX = {};\n",
+        serde_json::to_string_pretty(&entries)?
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::generate;
+    use crate::books::{Author, Book, Publisher};
+    use crate::covers::Cover;
+    use assert_fs::prelude::*;
+    use predicates::prelude::*;
+
+    fn author(id: u32, forename: &str, surname: &str) -> Author {
+        Author {
+            id,
+            forename: forename.to_string(),
+            surname: surname.to_string(),
+        }
+    }
+
+    fn books() -> Vec<Book> {
+        vec![
+            Book {
+                id: 1,
+                authors: vec![author(1, "Jim", "Dwyer"), author(2, "Peg", "Tyre")],
+                publisher: Publisher {
+                    id: 1,
+                    name: "Crown".to_string(),
+                },
+                title: "Two Seconds Under the World".to_string(),
+                edition: "1st".to_string(),
+                date_published: "1994".to_string(),
+                binding: "Hardcover".to_string(),
+                isbn: Some("9780517597675".to_string()),
+                pages: 322,
+                owned: true,
+                rating: Some(4),
+                shelf: Some("read".to_string()),
+                date_read: Some("2021-03-14".to_string()),
+                tags: vec!["Terrorism".to_string(), "New York".to_string()],
+                ..Default::default()
+            },
+            Book {
+                id: 2,
+                authors: vec![author(3, "", "Homer")],
+                publisher: Publisher {
+                    id: 2,
+                    name: "Penguin <Classics>".to_string(),
+                },
+                title: "The Odyssey".to_string(),
+                date_published: "1996".to_string(),
+                original_date_published: Some("-700".to_string()),
+                binding: "Paperback".to_string(),
+                owned: false,
+                ..Default::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn generate_should_write_a_page_for_each_book_author_and_publisher() {
+        let output_dir = assert_fs::TempDir::new().unwrap();
+        let pages = generate(&books(), output_dir.path()).unwrap();
+
+        // The index, 2 books, 3 authors, 2 publishers and the author and publisher lists
+        assert_eq!(pages, 10);
+        for path in [
+            "index.html",
+            "books/1.html",
+            "books/2.html",
+            "authors/3.html",
+            "publishers/2.html",
+            "authors.html",
+            "publishers.html",
+            "style.css",
+            "site.js",
+            "search-index.js",
+        ] {
+            output_dir.child(path).assert(predicates::path::is_file());
+        }
+    }
+
+    #[test]
+    fn generated_pages_should_match_the_snapshots() {
+        let output_dir = assert_fs::TempDir::new().unwrap();
+        generate(&books(), output_dir.path()).unwrap();
+        let read = |path: &str| std::fs::read_to_string(output_dir.path().join(path)).unwrap();
+
+        insta::assert_snapshot!("index", read("index.html"));
+        insta::assert_snapshot!("book", read("books/1.html"));
+        insta::assert_snapshot!("author", read("authors/3.html"));
+        insta::assert_snapshot!("publishers", read("publishers.html"));
+        insta::assert_snapshot!("search_index", read("search-index.js"));
+    }
+
+    #[test]
+    fn generate_should_copy_downloaded_covers_into_the_site() {
+        let storage_dir = assert_fs::TempDir::new().unwrap();
+        let cover_file = storage_dir.child("covers").child("abc123.jpg");
+        cover_file.write_binary(b"cover").unwrap();
+        let mut books = books();
+        books[0].cover = Some(Cover {
+            path: cover_file.to_path_buf(),
+            hash: "abc123".to_string(),
+        });
+        // A cover whose file has gone is left out rather than linked to.
+        books[1].cover = Some(Cover {
+            path: storage_dir.path().join("covers").join("gone.jpg"),
+            hash: "gone".to_string(),
+        });
+
+        let output_dir = assert_fs::TempDir::new().unwrap();
+        generate(&books, output_dir.path()).unwrap();
+
+        output_dir
+            .child("covers/abc123.jpg")
+            .assert(predicates::path::is_file());
+        output_dir
+            .child("books/1.html")
+            .assert(predicates::str::contains(
+                r#"<img class="cover" src="../covers/abc123.jpg""#,
+            ));
+        output_dir
+            .child("books/2.html")
+            .assert(predicates::str::contains("<img").not());
+    }
+}
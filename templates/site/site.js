@@ -0,0 +1,48 @@
+// Sorts the book tables when a column heading is clicked, and searches the books using the index
+// in search-index.js. Everything runs in the browser, so the site works without a server.
+(function () {
+  document.querySelectorAll("table.books").forEach(function (table) {
+    var body = table.querySelector("tbody");
+    table.querySelectorAll("button[data-sort]").forEach(function (button) {
+      button.addEventListener("click", function () {
+        var key = button.dataset.sort;
+        var descending = button.dataset.order === "ascending";
+        button.dataset.order = descending ? "descending" : "ascending";
+        Array.from(body.rows)
+          .sort(function (a, b) {
+            var order = a.dataset[key].localeCompare(b.dataset[key], undefined, { numeric: true });
+            return descending ? -order : order;
+          })
+          .forEach(function (row) {
+            body.appendChild(row);
+          });
+      });
+    });
+  });
+
+  var search = document.getElementById("search");
+  var results = document.getElementById("search-results");
+  if (!search || !window.SEARCH_INDEX) {
+    return;
+  }
+  search.addEventListener("input", function () {
+    var terms = search.value.toLowerCase().split(/\s+/).filter(Boolean);
+    results.innerHTML = "";
+    if (terms.length === 0) {
+      return;
+    }
+    window.SEARCH_INDEX.filter(function (entry) {
+      return terms.every(function (term) {
+        return entry.text.indexOf(term) !== -1;
+      });
+    }).forEach(function (entry) {
+      var item = document.createElement("li");
+      var link = document.createElement("a");
+      link.href = entry.url;
+      link.textContent = entry.title;
+      item.appendChild(link);
+      item.appendChild(document.createTextNode(" by " + entry.authors));
+      results.appendChild(item);
+    });
+  });
+})();